@@ -0,0 +1,59 @@
+//! Montgomery arithmetic against `num-bigint` for 256 to 4096 bit moduli.
+//!
+//! Run with `cargo bench --bench montgomery`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use montgomery_chaos_lattices::montgomery::MontgomeryContext;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::One;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+const MODULUS_BITS: [u64; 5] = [256, 512, 1024, 2048, 4096];
+
+/// A random odd modulus of exactly `bits` bits with a base and exponent below it
+fn operands(bits: u64) -> (BigUint, BigUint, BigUint) {
+    let mut rng = ChaCha20Rng::seed_from_u64(bits);
+    let modulus = rng.gen_biguint(bits) | BigUint::one() | (BigUint::one() << (bits - 1));
+    let base = rng.gen_biguint_below(&modulus);
+    let exponent = rng.gen_biguint_below(&modulus);
+    (modulus, base, exponent)
+}
+
+fn multiplication(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mul");
+    for bits in MODULUS_BITS {
+        let (modulus, a, b) = operands(bits);
+        let context = MontgomeryContext::new(&modulus).unwrap();
+        let (mont_a, mont_b) = (context.to_mont(&a), context.to_mont(&b));
+
+        group.bench_with_input(BenchmarkId::new("mont_mul", bits), &bits, |bencher, _| {
+            bencher.iter(|| context.mont_mul(black_box(&mont_a), black_box(&mont_b)))
+        });
+        group.bench_with_input(BenchmarkId::new("num_bigint", bits), &bits, |bencher, _| {
+            bencher.iter(|| black_box(&a) * black_box(&b) % &modulus)
+        });
+    }
+    group.finish();
+}
+
+fn exponentiation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pow");
+    group.sample_size(10);
+    for bits in MODULUS_BITS {
+        let (modulus, base, exponent) = operands(bits);
+        let context = MontgomeryContext::new(&modulus).unwrap();
+        let mont_base = context.to_mont(&base);
+
+        group.bench_with_input(BenchmarkId::new("mont_pow", bits), &bits, |bencher, _| {
+            bencher.iter(|| context.mont_pow(black_box(&mont_base), black_box(&exponent)))
+        });
+        group.bench_with_input(BenchmarkId::new("num_bigint", bits), &bits, |bencher, _| {
+            bencher.iter(|| black_box(&base).modpow(black_box(&exponent), &modulus))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, multiplication, exponentiation);
+criterion_main!(benches);
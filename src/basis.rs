@@ -0,0 +1,345 @@
+//! Integer lattice bases with exact Gram–Schmidt and LLL reduction.
+//!
+//! A `LatticeBasis` holds linearly independent rows over `BigInt`.
+//! Gram–Schmidt coefficients are exact `BigRational`s, and determinants use
+//! fraction-free (Bareiss) elimination, so nothing is lost to rounding.
+//! Quality measures that can overflow `f64`, such as the covolume, are
+//! computed from base-2 logarithms of the exact values.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+
+use crate::error::{Error, Result};
+use crate::lattice::Lattice;
+
+/// Lovász parameter used when none is given
+pub const DEFAULT_DELTA: f64 = 0.99;
+
+/// A lattice basis: linearly independent integer rows of equal length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatticeBasis {
+    rows: Vec<Vec<BigInt>>,
+}
+
+/// Exact Gram–Schmidt orthogonalization `b_i = b*_i + sum_{j<i} mu[i][j] b*_j`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GramSchmidt {
+    /// The orthogonal vectors `b*_i`
+    pub orthogonal: Vec<Vec<BigRational>>,
+    /// Projection coefficients `mu[i][j] = <b_i, b*_j> / <b*_j, b*_j>` for `j < i`
+    pub mu: Vec<Vec<BigRational>>,
+    /// Squared norms `<b*_i, b*_i>`
+    pub norms_squared: Vec<BigRational>,
+}
+
+/// Outcome of an LLL reduction
+#[derive(Debug, Clone, PartialEq)]
+pub struct LllReduction {
+    pub basis: LatticeBasis,
+    /// Number of Lovász swaps, a measure of how far from reduced the input was
+    pub swaps: usize,
+}
+
+/// Dot product of two integer vectors
+pub fn dot(a: &[BigInt], b: &[BigInt]) -> BigInt {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn rational_dot(a: &[BigRational], b: &[BigRational]) -> BigRational {
+    a.iter().zip(b).fold(BigRational::zero(), |sum, (x, y)| sum + x * y)
+}
+
+/// Base-2 logarithm of a positive integer, accurate for any size
+pub fn log2_big(x: &BigInt) -> f64 {
+    let shift = x.bits().saturating_sub(64);
+    let top = (x >> shift).to_f64().unwrap_or(f64::NAN);
+    top.log2() + shift as f64
+}
+
+/// Base-2 logarithm of a positive rational
+fn log2_rational(x: &BigRational) -> f64 {
+    log2_big(x.numer()) - log2_big(x.denom())
+}
+
+/// Determinant of a square integer matrix by Bareiss elimination
+fn bareiss_determinant(mut matrix: Vec<Vec<BigInt>>) -> BigInt {
+    let n = matrix.len();
+    let mut sign = BigInt::one();
+    let mut previous = BigInt::one();
+
+    for k in 0..n {
+        if matrix[k][k].is_zero() {
+            match (k + 1..n).find(|&i| !matrix[i][k].is_zero()) {
+                Some(i) => {
+                    matrix.swap(k, i);
+                    sign = -sign;
+                }
+                None => return BigInt::zero(),
+            }
+        }
+        for i in k + 1..n {
+            for j in k + 1..n {
+                // Exact: each entry is a minor of the original matrix
+                matrix[i][j] = (&matrix[i][j] * &matrix[k][k] - &matrix[i][k] * &matrix[k][j]) / &previous;
+            }
+        }
+        previous = matrix[k][k].clone();
+    }
+    sign * previous
+}
+
+impl LatticeBasis {
+    /// Create a basis, rejecting empty, ragged or linearly dependent rows
+    pub fn new(rows: Vec<Vec<BigInt>>) -> Result<Self> {
+        let dimension = rows.first().map_or(0, Vec::len);
+        if dimension == 0 || rows.iter().any(|row| row.len() != dimension) {
+            return Err(Error::Lattice("A basis needs non-empty rows of equal length.".to_string()));
+        }
+        if rows.len() > dimension {
+            return Err(Error::Lattice(format!("{} rows in dimension {} are linearly dependent.", rows.len(), dimension)));
+        }
+        let basis = Self { rows };
+        if basis.gram_determinant().is_zero() {
+            return Err(Error::Lattice("The basis rows are linearly dependent.".to_string()));
+        }
+        Ok(basis)
+    }
+
+    /// Wrap rows already known to be independent, such as the image of a
+    /// basis under a unimodular transformation
+    pub(crate) fn from_independent_rows(rows: Vec<Vec<BigInt>>) -> Self {
+        Self { rows }
+    }
+
+    /// Create a basis from unsigned rows
+    pub fn from_unsigned(rows: &[Vec<BigUint>]) -> Result<Self> {
+        Self::new(rows.iter().map(|row| row.iter().map(|x| BigInt::from_biguint(Sign::Plus, x.clone())).collect()).collect())
+    }
+
+    /// The basis spanned by the points of `lattice`
+    pub fn from_lattice(lattice: &Lattice) -> Result<Self> {
+        Self::from_unsigned(&lattice.get_coordinates())
+    }
+
+    /// Basis rows
+    pub fn rows(&self) -> &[Vec<BigInt>] {
+        &self.rows
+    }
+
+    /// Number of basis vectors
+    pub fn rank(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Length of each basis vector
+    pub fn dimension(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    /// The Gram matrix `G[i][j] = <b_i, b_j>`
+    pub fn gram_matrix(&self) -> Vec<Vec<BigInt>> {
+        self.rows.iter().map(|a| self.rows.iter().map(|b| dot(a, b)).collect()).collect()
+    }
+
+    /// `det(G)`, the squared covolume of the lattice
+    pub fn gram_determinant(&self) -> BigInt {
+        bareiss_determinant(self.gram_matrix())
+    }
+
+    /// Determinant of a square basis matrix
+    pub fn determinant(&self) -> Result<BigInt> {
+        if self.rank() != self.dimension() {
+            return Err(Error::Lattice(format!("A {}x{} basis has no determinant; use the covolume.", self.rank(), self.dimension())));
+        }
+        Ok(bareiss_determinant(self.rows.clone()))
+    }
+
+    /// Base-2 logarithm of the covolume `sqrt(det(G))`
+    pub fn log2_covolume(&self) -> f64 {
+        log2_big(&self.gram_determinant()) / 2.0
+    }
+
+    /// Euclidean norm of every basis vector, as a base-2 logarithm
+    pub fn log2_norms(&self) -> Vec<f64> {
+        self.rows.iter().map(|row| log2_big(&dot(row, row)) / 2.0).collect()
+    }
+
+    /// Exact Gram–Schmidt orthogonalization
+    pub fn gram_schmidt(&self) -> GramSchmidt {
+        let n = self.rank();
+        let mut orthogonal: Vec<Vec<BigRational>> = Vec::with_capacity(n);
+        let mut mu = vec![vec![BigRational::zero(); n]; n];
+        let mut norms_squared: Vec<BigRational> = Vec::with_capacity(n);
+
+        for (i, row) in self.rows.iter().enumerate() {
+            let row: Vec<BigRational> = row.iter().map(|x| BigRational::from_integer(x.clone())).collect();
+            let mut star = row.clone();
+            for j in 0..i {
+                mu[i][j] = rational_dot(&row, &orthogonal[j]) / &norms_squared[j];
+                for (x, y) in star.iter_mut().zip(&orthogonal[j]) {
+                    *x -= &mu[i][j] * y;
+                }
+            }
+            mu[i][i] = BigRational::one();
+            norms_squared.push(rational_dot(&star, &star));
+            orthogonal.push(star);
+        }
+        GramSchmidt { orthogonal, mu, norms_squared }
+    }
+
+    /// Base-2 logarithms of the Gram–Schmidt norms `||b*_i||`, the basis profile
+    pub fn log2_profile(&self) -> Vec<f64> {
+        self.gram_schmidt().norms_squared.iter().map(|norm| log2_rational(norm) / 2.0).collect()
+    }
+
+    /// Orthogonality defect `prod ||b_i|| / covolume`: 1 for an orthogonal
+    /// basis, larger the more skewed it is (infinite if beyond `f64`)
+    pub fn orthogonality_defect(&self) -> f64 {
+        (self.log2_norms().iter().sum::<f64>() - self.log2_covolume()).exp2()
+    }
+
+    /// Hermite factor `||b_1|| / covolume^(1/n)` of the first basis vector
+    pub fn hermite_factor(&self) -> f64 {
+        (self.log2_norms()[0] - self.log2_covolume() / self.rank() as f64).exp2()
+    }
+
+    /// Root Hermite factor `hermite_factor^(1/n)`, the usual measure of
+    /// reduction quality (about 1.0219 for LLL in practice)
+    pub fn root_hermite_factor(&self) -> f64 {
+        self.hermite_factor().powf(1.0 / self.rank() as f64)
+    }
+
+    /// Whether the basis is size-reduced and satisfies the Lovász condition for `delta`
+    pub fn is_lll_reduced(&self, delta: f64) -> Result<bool> {
+        let delta = lovasz_parameter(delta)?;
+        let half = BigRational::new(BigInt::one(), BigInt::from(2));
+        let gs = self.gram_schmidt();
+        let size_reduced = (0..self.rank()).all(|i| (0..i).all(|j| gs.mu[i][j].abs() <= half));
+        let lovasz = (1..self.rank()).all(|k| {
+            let mu = &gs.mu[k][k - 1];
+            gs.norms_squared[k] >= (&delta - mu * mu) * &gs.norms_squared[k - 1]
+        });
+        Ok(size_reduced && lovasz)
+    }
+
+    /// LLL-reduce the basis with Lovász parameter `delta` in `(1/4, 1]`
+    pub fn lll(&self, delta: f64) -> Result<LllReduction> {
+        let delta = lovasz_parameter(delta)?;
+        let n = self.rank();
+        let mut rows = self.rows.clone();
+        let gs = self.gram_schmidt();
+        let (mut mu, mut b) = (gs.mu, gs.norms_squared);
+        let half = BigRational::new(BigInt::one(), BigInt::from(2));
+        let mut swaps = 0;
+
+        let mut k = 1;
+        while k < n {
+            // Size-reduce b_k against b_{k-1}, ..., b_0
+            for j in (0..k).rev() {
+                if mu[k][j].abs() > half {
+                    let q = mu[k][j].round();
+                    let q_int = q.to_integer();
+                    let (head, tail) = rows.split_at_mut(k);
+                    for (x, y) in tail[0].iter_mut().zip(&head[j]) {
+                        *x -= &q_int * y;
+                    }
+                    let (head, tail) = mu.split_at_mut(k);
+                    for (x, y) in tail[0].iter_mut().zip(&head[j]).take(j) {
+                        *x -= &q * y;
+                    }
+                    tail[0][j] -= q;
+                }
+            }
+
+            let mu_k = mu[k][k - 1].clone();
+            if b[k] >= (&delta - &mu_k * &mu_k) * &b[k - 1] {
+                k += 1;
+                continue;
+            }
+
+            // Swap b_k and b_{k-1} and update the Gram–Schmidt data in place
+            swaps += 1;
+            rows.swap(k, k - 1);
+            let new_b = &b[k] + &mu_k * &mu_k * &b[k - 1];
+            mu[k][k - 1] = &mu_k * &b[k - 1] / &new_b;
+            b[k] = &b[k - 1] * &b[k] / &new_b;
+            b[k - 1] = new_b;
+            for j in 0..k - 1 {
+                let (upper, lower) = mu.split_at_mut(k);
+                std::mem::swap(&mut upper[k - 1][j], &mut lower[0][j]);
+            }
+            for i in k + 1..n {
+                let t = mu[i][k].clone();
+                mu[i][k] = &mu[i][k - 1] - &mu_k * &t;
+                mu[i][k - 1] = t + &mu[k][k - 1] * &mu[i][k];
+            }
+            k = (k - 1).max(1);
+        }
+
+        Ok(LllReduction { basis: Self { rows }, swaps })
+    }
+}
+
+/// `delta` as an exact rational, checked to lie in `(1/4, 1]`
+fn lovasz_parameter(delta: f64) -> Result<BigRational> {
+    if !(delta > 0.25 && delta <= 1.0) {
+        return Err(Error::Lattice(format!("The LLL parameter {} is outside (1/4, 1].", delta)));
+    }
+    BigRational::from_f64(delta).ok_or_else(|| Error::Lattice(format!("The LLL parameter {} is not finite.", delta)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn basis(rows: &[&[i64]]) -> LatticeBasis {
+        LatticeBasis::new(rows.iter().map(|row| row.iter().map(|&x| BigInt::from(x)).collect()).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_gram_schmidt_and_invariants() {
+        let b = basis(&[&[3, 1], &[2, 2]]);
+        let gs = b.gram_schmidt();
+        assert_eq!(gs.mu[1][0], BigRational::new(BigInt::from(8), BigInt::from(10)));
+        assert_eq!(gs.norms_squared[1], BigRational::new(BigInt::from(16), BigInt::from(10)));
+        assert_eq!(rational_dot(&gs.orthogonal[0], &gs.orthogonal[1]), BigRational::zero());
+        assert_eq!(b.gram_matrix(), vec![vec![BigInt::from(10), BigInt::from(8)], vec![BigInt::from(8), BigInt::from(8)]]);
+        assert_eq!(b.determinant().unwrap(), BigInt::from(4));
+        assert_eq!(b.gram_determinant(), BigInt::from(16));
+        assert!((b.log2_covolume() - 2.0).abs() < 1e-12);
+        // sqrt(10) * sqrt(8) / 4
+        assert!((b.orthogonality_defect() - 80f64.sqrt() / 4.0).abs() < 1e-9);
+        assert!((basis(&[&[2, 0, 0], &[0, 3, 0], &[0, 0, 5]]).orthogonality_defect() - 1.0).abs() < 1e-12);
+
+        assert!(LatticeBasis::new(vec![vec![BigInt::from(1), BigInt::from(2)], vec![BigInt::from(2), BigInt::from(4)]]).is_err());
+        assert!(basis(&[&[1, 2, 3]]).determinant().is_err());
+    }
+
+    #[test]
+    fn test_lll_reduces_and_preserves_the_lattice() {
+        // A skewed basis of Z^3 scaled by a diagonal
+        let b = basis(&[&[1, 0, 0], &[4, 1, 0], &[13, 7, 1]]);
+        let scaled = basis(&[&[1, 0, 0], &[4, 2, 0], &[13, 14, 3]]);
+        for input in [&b, &scaled] {
+            let reduced = input.lll(DEFAULT_DELTA).unwrap();
+            assert!(reduced.basis.is_lll_reduced(DEFAULT_DELTA).unwrap());
+            assert!(!input.is_lll_reduced(DEFAULT_DELTA).unwrap());
+            assert_eq!(reduced.basis.determinant().unwrap().abs(), input.determinant().unwrap().abs());
+            assert!(reduced.basis.orthogonality_defect() < input.orthogonality_defect());
+        }
+        assert_eq!(b.lll(DEFAULT_DELTA).unwrap().basis.orthogonality_defect(), 1.0);
+
+        // A knapsack-style basis, whose reduced form has short rows
+        let knapsack = basis(&[&[1, 0, 0, 0, 366], &[0, 1, 0, 0, 385], &[0, 0, 1, 0, 392], &[0, 0, 0, 1, 401]]);
+        let reduction = knapsack.lll(0.75).unwrap();
+        assert!(reduction.swaps > 0);
+        let reduced = reduction.basis;
+        assert!(reduced.is_lll_reduced(0.75).unwrap());
+        assert_eq!(reduced.gram_determinant(), knapsack.gram_determinant());
+        assert!(reduced.hermite_factor() < knapsack.hermite_factor());
+
+        assert!(b.lll(0.25).is_err());
+        assert!(b.lll(1.5).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! dudect-style timing leakage test for the lattice binding ladders.
+//!
+//! Each measurement times one ladder call on either a fixed scalar (zero)
+//! or a fresh random scalar, picked at random so drift affects both classes
+//! alike. Welch's t-test then compares the two timing distributions, raw
+//! and cropped at several percentiles to remove interrupt noise. A |t|
+//! above `THRESHOLD` means the timings depend on the scalar. Run it with
+//! `--release` on an otherwise idle machine.
+//!
+//! `bind_with_chaos_constant_time`, which the key schedule uses, is measured
+//! end to end, including its variable-time conversions between `BigUint`
+//! and `Fq`: those depend on the coordinates, not on the scalar.
+
+use std::env;
+use std::hint::black_box;
+use std::time::Instant;
+
+use montgomery_chaos_lattices::chaos::chaotic_sequence;
+use montgomery_chaos_lattices::hyper_prime::small_prime_sieve;
+use montgomery_chaos_lattices::ladder::{self, Fq, U256};
+use montgomery_chaos_lattices::lattice::Lattice;
+use montgomery_chaos_lattices::stats::welch_t;
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use rand::Rng;
+
+/// |t| beyond which the two classes are considered distinguishable
+const THRESHOLD: f64 = 4.5;
+
+/// Percentiles at which measurements are cropped, 100 meaning no cropping
+const CROP_PERCENTILES: [f64; 5] = [100.0, 99.0, 90.0, 75.0, 50.0];
+
+/// Untimed calls before measuring, to warm caches and the branch predictor
+const WARMUP: usize = 1_000;
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} [measurements] [dimensions]", program);
+    eprintln!("Defaults: 20000 measurements per target, 4 dimensions");
+}
+
+/// Largest |t| over all crops, with the crop it was found at
+fn max_t(fixed: &[f64], random: &[f64]) -> (f64, f64) {
+    let mut all: Vec<f64> = fixed.iter().chain(random).copied().collect();
+    all.sort_by(f64::total_cmp);
+
+    CROP_PERCENTILES
+        .iter()
+        .map(|&percentile| {
+            let cutoff = all[((all.len() - 1) as f64 * percentile / 100.0) as usize];
+            let keep = |samples: &[f64]| samples.iter().copied().filter(|&x| x <= cutoff).collect::<Vec<f64>>();
+            (welch_t(&keep(fixed), &keep(random)).abs(), percentile)
+        })
+        .filter(|(t, _)| t.is_finite())
+        .fold((0.0, 100.0), |best, current| if current.0 > best.0 { current } else { best })
+}
+
+/// Time `run` on `measurements` inputs of randomly chosen class, returning
+/// the timings of the fixed and the random class in nanoseconds.
+///
+/// All inputs are prepared before the first timing so that generating them
+/// does not disturb the caches of one class only.
+fn measure<T: Clone>(measurements: usize, fixed: T, random: impl Fn() -> T, run: impl Fn(&T)) -> (Vec<f64>, Vec<f64>) {
+    for _ in 0..WARMUP {
+        run(&random());
+    }
+
+    let classes: Vec<bool> = (0..measurements).map(|_| OsRng.gen()).collect();
+    let inputs: Vec<T> = classes.iter().map(|&is_fixed| if is_fixed { fixed.clone() } else { random() }).collect();
+
+    let (mut fixed_times, mut random_times) = (Vec::new(), Vec::new());
+    for (is_fixed, input) in classes.into_iter().zip(&inputs) {
+        let start = Instant::now();
+        run(black_box(input));
+        let elapsed = start.elapsed().as_nanos() as f64;
+
+        if is_fixed {
+            fixed_times.push(elapsed);
+        } else {
+            random_times.push(elapsed);
+        }
+    }
+    (fixed_times, random_times)
+}
+
+/// Print the verdict for one target and return whether it leaked
+fn report(name: &str, (fixed, random): (Vec<f64>, Vec<f64>)) -> bool {
+    let mean = |samples: &[f64]| samples.iter().sum::<f64>() / samples.len() as f64;
+    let (t, percentile) = max_t(&fixed, &random);
+    let leaks = t > THRESHOLD;
+    println!(
+        "{:<30} fixed {:>10.0} ns  random {:>10.0} ns  max |t| = {:>8.2} (crop {:>3}%)  {}",
+        name,
+        mean(&fixed),
+        mean(&random),
+        t,
+        percentile,
+        if leaks { "LEAK" } else { "no leak detected" }
+    );
+    leaks
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let parse = |index: usize, default: usize| match args.get(index) {
+        None => default,
+        Some(value) => value.parse().unwrap_or_else(|_| {
+            eprintln!("Error: '{}' is not a valid number.", value);
+            print_usage(&args[0]);
+            std::process::exit(1);
+        }),
+    };
+    let measurements = parse(1, 20_000);
+    let dimensions = parse(2, 4);
+    if measurements < 2 || dimensions == 0 {
+        print_usage(&args[0]);
+        std::process::exit(1);
+    }
+
+    let small_primes = small_prime_sieve(10_000);
+    let lattice = match Lattice::from_key_material(b"dudect", dimensions, 1, 256, &small_primes, 20) {
+        Ok(lattice) => lattice,
+        Err(e) => {
+            eprintln!("Error creating lattice: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let row: Vec<Fq> = lattice.get_coordinates()[0].iter().map(ladder::fq_from_biguint).collect();
+    let random_bytes = || OsRng.gen::<[u8; 32]>();
+
+    println!("{} measurements per target, {} dimensions, threshold |t| > {}", measurements, dimensions, THRESHOLD);
+
+    let constant_time = measure(
+        measurements,
+        U256::ZERO,
+        || U256::from_be_slice(&random_bytes()),
+        |scalar| {
+            black_box(ladder::ladder(scalar, &row));
+        },
+    );
+    let leaks = report("ladder (constant time)", constant_time);
+
+    let chaos_seq = chaotic_sequence(dimensions, 42);
+    let bound = measure(
+        measurements,
+        U256::ZERO,
+        || U256::from_be_slice(&random_bytes()),
+        |scalar| {
+            let mut bound = lattice.clone();
+            bound.bind_with_chaos_constant_time(scalar, &chaos_seq);
+            black_box(bound);
+        },
+    );
+    let leaks = report("bind_with_chaos_constant_time", bound) | leaks;
+
+    let legacy = measure(
+        measurements,
+        BigUint::from(0u32),
+        || BigUint::from_bytes_be(&random_bytes()),
+        |scalar| {
+            let mut bound = lattice.clone();
+            let _ = black_box(bound.bind_rows_with_ladder(scalar));
+        },
+    );
+    report("bind_rows_with_ladder", legacy);
+
+    if leaks {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,78 @@
+use montgomery_chaos_lattices::hyper_prime::{
+    generate_germain_prime, generate_hyper_prime, generate_mersenne_prime, generate_safe_prime,
+    small_prime_sieve,
+};
+use rand::rngs::OsRng;
+use std::env;
+use std::time::Instant;
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} <number_of_bits> <prime_type>", program);
+    eprintln!("Prime types: prime, safe, germain, mersenne");
+}
+
+fn main() {
+    let start = Instant::now();
+    let args: Vec<String> = env::args().collect();
+    let small_prime_limit = 10_000;
+
+    if args.len() < 3 {
+        print_usage(&args[0]);
+        std::process::exit(1);
+    }
+
+    // Parse number_of_bits
+    let bits: usize = match args[1].parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("Error: '{}' is not a valid number of bits.", args[1]);
+            print_usage(&args[0]);
+            std::process::exit(1);
+        }
+    };
+
+    // Parse prime_type
+    let prime_type = args[2].to_lowercase();
+    let valid_prime_types = ["prime", "safe", "germain", "mersenne"];
+
+    if !valid_prime_types.contains(&prime_type.as_str()) {
+        eprintln!("Error: '{}' is not a supported prime type.", args[2]);
+        print_usage(&args[0]);
+        std::process::exit(1);
+    }
+
+    println!("Generating a '{}' prime with {} bits.", prime_type, bits);
+
+    let small_primes = small_prime_sieve(small_prime_limit);
+    let mut rng = OsRng;
+    let rounds = 40; // Increased for better security
+
+    let result = match prime_type.as_str() {
+        "prime" => generate_hyper_prime(&mut rng, bits, &small_primes, rounds),
+        "safe" => generate_safe_prime(&mut rng, bits, &small_primes, rounds),
+        "germain" => generate_germain_prime(&mut rng, bits, &small_primes, rounds),
+        // For Mersenne primes, bits correspond to the exponent
+        // Mersenne primes have the form 2^n - 1, where n is prime
+        // Note: Generating large Mersenne primes is computationally intensive
+        "mersenne" => generate_mersenne_prime(&mut rng, bits, &small_primes, rounds),
+        _ => {
+            // This should never happen due to earlier validation
+            eprintln!("Unsupported prime type: {}", prime_type);
+            std::process::exit(1);
+        }
+    };
+
+    let hyper_prime = match result {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let duration = start.elapsed();
+
+    println!("Generated {} prime ({} bits): {}", prime_type, bits, hyper_prime);
+    println!("Time taken: {:?}", duration);
+}
+
@@ -0,0 +1,322 @@
+//! BKZ reduction with enumeration.
+//!
+//! A tour slides a window of `block_size` vectors along an LLL-reduced
+//! basis and enumerates each block, projected orthogonally to the vectors
+//! before it, for its shortest vector (Schnorr–Euchner zig-zag over
+//! floating-point Gram–Schmidt data). A vector shorter than
+//! `sqrt(delta) ||b*_k||` is moved to the front of the block by a unimodular
+//! transformation and the basis is LLL-reduced again. As in BKZ 2.0, the
+//! enumeration radius is capped at a multiple of the block's Gaussian
+//! heuristic, the search tree can be pruned, and the reduction aborts
+//! early after a number of tours or once the profile stops improving.
+
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Signed, Zero};
+
+use crate::basis::{log2_big, GramSchmidt, LatticeBasis, DEFAULT_DELTA};
+use crate::error::{Error, Result};
+use crate::estimator::log2_gaussian_heuristic;
+
+/// Relative slope gain below which a tour counts towards the auto-abort
+const AUTO_ABORT_GAIN: f64 = 1e-3;
+
+/// BKZ parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct BkzParams {
+    /// Number of vectors enumerated together (capped at the rank)
+    pub block_size: usize,
+    /// Lovász parameter for LLL, also the gain needed to insert a vector
+    pub delta: f64,
+    /// Stop after this many tours
+    pub max_tours: Option<usize>,
+    /// Stop once this many tours in a row barely improve the profile slope.
+    /// At least one of `max_tours` and `auto_abort` must be set, since
+    /// floating-point enumeration can keep inserting without converging.
+    pub auto_abort: Option<usize>,
+    /// Cap the enumeration radius at this multiple of the Gaussian heuristic
+    pub gh_factor: Option<f64>,
+    /// Linear pruning: bound level `i` of a block of size `β` by `(β - i) / β` of the squared radius
+    pub pruning: bool,
+}
+
+impl Default for BkzParams {
+    /// BKZ-10 with a radius of 1.1 Gaussian heuristics, no pruning, and an
+    /// abort after five tours without progress
+    fn default() -> Self {
+        Self {
+            block_size: 10,
+            delta: DEFAULT_DELTA,
+            max_tours: None,
+            auto_abort: Some(5),
+            gh_factor: Some(1.1),
+            pruning: false,
+        }
+    }
+}
+
+/// Outcome of a BKZ reduction
+#[derive(Debug, Clone, PartialEq)]
+pub struct BkzReduction {
+    pub basis: LatticeBasis,
+    /// Block size actually used
+    pub block_size: usize,
+    pub tours: usize,
+    /// Number of enumerated vectors inserted into the basis
+    pub insertions: usize,
+    /// Enumeration tree nodes visited
+    pub nodes: u64,
+    /// Whether an early-abort rule stopped the reduction before a tour without insertions
+    pub aborted: bool,
+}
+
+/// A rational as the nearest `f64`, by way of logarithms so large values do not overflow
+fn rational_to_f64(x: &BigRational) -> f64 {
+    if x.is_zero() {
+        return 0.0;
+    }
+    let log2 = log2_big(&x.numer().abs()) - log2_big(x.denom());
+    log2.exp2().copysign(if x.is_negative() { -1.0 } else { 1.0 })
+}
+
+/// Least-squares slope of the basis profile, close to zero for a well-reduced basis
+fn profile_slope(profile: &[f64]) -> f64 {
+    let n = profile.len() as f64;
+    if profile.len() < 2 {
+        return 0.0;
+    }
+    let mean_i = (n - 1.0) / 2.0;
+    let mean_y = profile.iter().sum::<f64>() / n;
+    let (covariance, variance) = profile.iter().enumerate().fold((0.0, 0.0), |(covariance, variance), (i, y)| {
+        let di = i as f64 - mean_i;
+        (covariance + di * (y - mean_y), variance + di * di)
+    });
+    covariance / variance
+}
+
+/// Coefficients of the shortest nonzero vector in the projected block `[start, end)`
+/// strictly shorter than the radius, and the number of nodes visited
+fn enumerate_block(gs: &GramSchmidt, start: usize, end: usize, params: &BkzParams) -> (Option<Vec<i64>>, u64) {
+    let n = end - start;
+    // Scale by ||b*_start||^2 so the block's values stay near one
+    let log2_norms: Vec<f64> = gs.norms_squared[start..end]
+        .iter()
+        .map(|norm| log2_big(norm.numer()) - log2_big(norm.denom()))
+        .collect();
+    let r: Vec<f64> = log2_norms.iter().map(|log2| (log2 - log2_norms[0]).exp2()).collect();
+    let mu: Vec<Vec<f64>> = (start..end).map(|i| (start..i).map(|j| rational_to_f64(&gs.mu[i][j])).collect()).collect();
+
+    let mut radius = params.delta;
+    if let Some(factor) = params.gh_factor {
+        let log2_volume = log2_norms.iter().sum::<f64>() / 2.0;
+        let log2_gh_squared = 2.0 * (factor.log2() + log2_gaussian_heuristic(n, log2_volume)) - log2_norms[0];
+        radius = radius.min(log2_gh_squared.exp2());
+    }
+    let bound = |k: usize, radius: f64| if params.pruning { radius * (n - k) as f64 / n as f64 } else { radius };
+
+    let mut x = vec![0i64; n];
+    let mut dx = vec![0i64; n];
+    let mut ddx = vec![0i64; n];
+    let mut center = vec![0.0; n];
+    // partial[k] is the squared length projected from level k up
+    let mut partial = vec![0.0; n + 1];
+    let mut best = None;
+    let mut nodes = 0u64;
+
+    x[0] = 1;
+    let mut k = 0;
+    loop {
+        nodes += 1;
+        let diff = x[k] as f64 - center[k];
+        let length = partial[k + 1] + diff * diff * r[k];
+        if length < bound(k, radius) {
+            if k == 0 {
+                radius = length;
+                best = Some(x.clone());
+            } else {
+                k -= 1;
+                partial[k + 1] = length;
+                center[k] = -(k + 1..n).map(|j| x[j] as f64 * mu[j][k]).sum::<f64>();
+                x[k] = center[k].round() as i64;
+                dx[k] = 0;
+                ddx[k] = if center[k] >= x[k] as f64 { -1 } else { 1 };
+                continue;
+            }
+        } else {
+            k += 1;
+            if k == n {
+                break;
+            }
+        }
+        if partial[k + 1] == 0.0 {
+            // Everything above is zero: only count up, skipping the negated vectors
+            x[k] += 1;
+        } else {
+            // Zig-zag outwards from the center
+            ddx[k] = -ddx[k];
+            dx[k] = ddx[k] - dx[k];
+            x[k] += dx[k];
+        }
+    }
+    (best, nodes)
+}
+
+/// Make `sum coefficients[i] * rows[i]` (divided by the coefficients' gcd)
+/// the first row by a unimodular transformation of `rows`
+fn insert(rows: &mut [Vec<BigInt>], coefficients: &[i64]) {
+    let mut c: Vec<BigInt> = coefficients.iter().map(|&x| BigInt::from(x)).collect();
+    for i in (1..rows.len()).rev() {
+        // Euclid on c[i-1], c[i]: b_i += q b_{i-1} keeps the vector while c[i-1] -= q c[i]
+        while !c[i].is_zero() {
+            let q = &c[i - 1] / &c[i];
+            let (head, tail) = rows.split_at_mut(i);
+            for (x, y) in tail[0].iter_mut().zip(&head[i - 1]) {
+                *x += &q * y;
+            }
+            let product = &q * &c[i];
+            c[i - 1] -= product;
+            rows.swap(i - 1, i);
+            c.swap(i - 1, i);
+        }
+    }
+}
+
+impl LatticeBasis {
+    /// BKZ-reduce the basis
+    pub fn bkz(&self, params: &BkzParams) -> Result<BkzReduction> {
+        if params.block_size < 2 {
+            return Err(Error::Lattice(format!("A BKZ block size of {} is below 2.", params.block_size)));
+        }
+        if params.max_tours.is_none() && params.auto_abort.is_none() {
+            return Err(Error::Lattice("BKZ needs a tour limit or an auto-abort rule to terminate.".to_string()));
+        }
+        let mut basis = self.lll(params.delta)?.basis;
+        let n = basis.rank();
+        let block_size = params.block_size.min(n);
+        let mut slope = profile_slope(&basis.log2_profile());
+        let (mut tours, mut insertions, mut nodes, mut stalls) = (0, 0, 0u64, 0);
+        let mut aborted = false;
+
+        loop {
+            if params.max_tours.is_some_and(|max_tours| tours >= max_tours) {
+                aborted = true;
+                break;
+            }
+            tours += 1;
+            let mut clean = true;
+            let mut gs = basis.gram_schmidt();
+            for k in 0..n.saturating_sub(1) {
+                let end = (k + block_size).min(n);
+                let (coefficients, visited) = enumerate_block(&gs, k, end, params);
+                nodes += visited;
+                if let Some(coefficients) = coefficients {
+                    let mut rows = basis.rows().to_vec();
+                    insert(&mut rows[k..end], &coefficients);
+                    basis = LatticeBasis::from_independent_rows(rows).lll(params.delta)?.basis;
+                    gs = basis.gram_schmidt();
+                    insertions += 1;
+                    clean = false;
+                }
+            }
+            if clean {
+                break;
+            }
+
+            let new_slope = profile_slope(&basis.log2_profile());
+            if let Some(limit) = params.auto_abort {
+                stalls = if new_slope > slope + AUTO_ABORT_GAIN * slope.abs() { 0 } else { stalls + 1 };
+                if stalls >= limit {
+                    aborted = true;
+                    break;
+                }
+            }
+            slope = new_slope;
+        }
+
+        Ok(BkzReduction { basis, block_size, tours, insertions, nodes, aborted })
+    }
+}
+
+impl fmt::Display for BkzReduction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shortest = self.basis.log2_norms().into_iter().fold(f64::INFINITY, f64::min);
+        writeln!(f, "BKZ-{} reduction", self.block_size)?;
+        writeln!(f, "  {:<34} {}{}", "Tours", self.tours, if self.aborted { " (aborted early)" } else { "" })?;
+        writeln!(f, "  {:<34} {}", "Insertions", self.insertions)?;
+        writeln!(f, "  {:<34} {}", "Enumeration nodes", self.nodes)?;
+        writeln!(f, "  {:<34} {:.6}", "Root Hermite factor", self.basis.root_hermite_factor())?;
+        writeln!(f, "  {:<34} 2^{:.2}", "Shortest vector", shortest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basis::dot;
+    use crate::hyper_prime::small_prime_sieve;
+    use crate::lattice::Lattice;
+    use num_bigint::BigUint;
+
+    fn basis(rows: &[&[i64]]) -> LatticeBasis {
+        LatticeBasis::new(rows.iter().map(|row| row.iter().map(|&x| BigInt::from(x)).collect()).collect()).unwrap()
+    }
+
+    #[test]
+    fn test_full_block_finds_the_shortest_vector() {
+        let knapsack = basis(&[&[1, 0, 0, 0, 0, 3660], &[0, 1, 0, 0, 0, 3851], &[0, 0, 1, 0, 0, 3927], &[0, 0, 0, 1, 0, 4013], &[0, 0, 0, 0, 1, 4107]]);
+        let params = BkzParams { block_size: 5, gh_factor: None, ..BkzParams::default() };
+        let reduction = knapsack.bkz(&params).unwrap();
+        let reduced = &reduction.basis;
+        assert!(!reduction.aborted);
+        assert!(reduced.is_lll_reduced(DEFAULT_DELTA).unwrap());
+        assert_eq!(reduced.gram_determinant(), knapsack.gram_determinant());
+
+        // Brute force over small combinations of the reduced basis
+        let rows = reduced.rows();
+        let mut shortest = dot(&rows[0], &rows[0]);
+        for index in 1..5i64.pow(5) {
+            let coefficients: Vec<i64> = (0..5).map(|i| (index / 5i64.pow(i)) % 5 - 2).collect();
+            let v: Vec<BigInt> = (0..6).map(|j| coefficients.iter().zip(rows).map(|(&c, row)| BigInt::from(c) * &row[j]).sum()).collect();
+            let norm = dot(&v, &v);
+            if !norm.is_zero() && norm < shortest {
+                shortest = norm;
+            }
+        }
+        assert_eq!(dot(&rows[0], &rows[0]), shortest);
+
+        assert!(knapsack.bkz(&BkzParams { block_size: 1, ..BkzParams::default() }).is_err());
+        assert!(matches!(knapsack.bkz(&BkzParams { max_tours: None, auto_abort: None, ..BkzParams::default() }), Err(Error::Lattice(_))));
+        let capped = knapsack.bkz(&BkzParams { max_tours: Some(0), ..BkzParams::default() }).unwrap();
+        assert!(capped.aborted && capped.tours == 0);
+    }
+
+    #[test]
+    fn test_bound_lattice_reduction() {
+        let small_primes = small_prime_sieve(10_000);
+        let lattice = Lattice::new(8, 6, 16, &small_primes, 20).unwrap();
+        let original = LatticeBasis::from_lattice(&lattice).unwrap();
+        let params = BkzParams { block_size: 4, pruning: true, ..BkzParams::default() };
+        let reference = original.bkz(&params).unwrap();
+
+        // Without chaos offsets the ladder scales every row by the same factor mod q, so BKZ sees the same lattice
+        let mut bound = lattice.clone();
+        bound.bind_rows_with_ladder(&BigUint::from(0xbeefu32)).unwrap();
+        let factor = &bound.get_coordinates()[0][0] / &lattice.get_coordinates()[0][0];
+        let reduction = LatticeBasis::from_lattice(&bound).unwrap().bkz(&params).unwrap();
+        let scaled: Vec<Vec<BigInt>> = reference.basis.rows().iter().map(|row| row.iter().map(|x| x * BigInt::from(factor.clone())).collect()).collect();
+        assert_eq!(reduction.basis.rows(), &scaled[..]);
+
+        // Over Z_q the rows stay independent and BKZ does at least as well as LLL
+        let mut modular = lattice.clone();
+        modular.generate_prime_anchors();
+        modular.set_modulus_from_anchors().unwrap();
+        modular.bind_rows_with_ladder(&BigUint::from(0xbeefu32)).unwrap();
+        let input = LatticeBasis::from_lattice(&modular).unwrap();
+        let reduction = input.bkz(&params).unwrap();
+        assert_eq!(reduction.basis.gram_determinant(), input.gram_determinant());
+        assert!(reduction.basis.log2_norms()[0] <= input.lll(DEFAULT_DELTA).unwrap().basis.log2_norms()[0] + 1e-9);
+        assert!(reduction.to_string().contains("BKZ-4 reduction"));
+    }
+}
@@ -0,0 +1,323 @@
+use sha3::{Digest, Sha3_256};
+use std::collections::HashMap;
+
+use crate::chaotic_map::{map_sequence, CatMap, ChebyshevMap, HenonMap, LogisticMap, TentMap, TrigonometricMap, WideCatMap};
+use crate::error::{Error, Result};
+use crate::mac::{Mac, MacAlgorithm};
+
+/// Generates a strong perturbation based on the current state and step using SHA3-256.
+pub fn enhanced_perturbation(state: u64, step: u64) -> u64 {
+    let input = format!("{}-{}", state, step);
+    let hash = Sha3_256::digest(input.as_bytes());
+    u64::from_be_bytes(hash[0..8].try_into().unwrap())
+}
+
+/// Generates a chaotic sequence using modular arithmetic and deterministic perturbation.
+///
+/// This is `SequenceAlgorithm::Trigonometric`, driven by `TrigonometricMap`.
+/// It goes through `f64` `sin` and `cos`, which are not guaranteed to round
+/// identically on every platform, and its state is reduced modulo `n` after
+/// the first step, so it is only kept to read existing data.
+pub fn chaotic_sequence(n: usize, seed: u64) -> Vec<usize> {
+    map_sequence::<TrigonometricMap>(n, seed)
+}
+
+/// Generates a chaotic permutation of `0..n` using integer arithmetic only.
+///
+/// The seed drives the cat map, and each state is hashed with its step to
+/// pick the swap of a Fisher–Yates shuffle, so the output is bit-exact on
+/// every platform.
+pub fn cat_map_sequence(n: usize, seed: u64) -> Vec<usize> {
+    map_sequence::<CatMap>(n, seed)
+}
+
+/// Generates a chaotic permutation of `0..n` from the 256-bit `WideCatMap`.
+///
+/// Like `cat_map_sequence` this is an integer-only Fisher–Yates shuffle, but
+/// all 256 bits of state carry through every step.
+pub fn wide_cat_map_sequence(n: usize, seed: u64) -> Vec<usize> {
+    map_sequence::<WideCatMap>(n, seed)
+}
+
+/// Versioned chaotic sequence algorithms, one per `ChaoticMap`.
+///
+/// This is the single map selector: the lattice S-Box and keystream and
+/// `encode_with_seed` all draw their sequence through it. Ciphertext formats
+/// record which algorithm produced their keystream, so data written with an
+/// older algorithm stays decryptable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceAlgorithm {
+    /// `chaotic_sequence`: floating-point, used by all data written before version 2
+    Trigonometric,
+    /// `cat_map_sequence`: integer-only and bit-exact, 64-bit state
+    CatMap,
+    /// `wide_cat_map_sequence`: integer-only and bit-exact, 256-bit state
+    WideCatMap,
+    /// `LogisticMap`, for comparing chaos sources
+    LogisticMap,
+    /// `TentMap`, for comparing chaos sources
+    TentMap,
+    /// `HenonMap`, for comparing chaos sources
+    HenonMap,
+    /// `ChebyshevMap`, for comparing chaos sources
+    ChebyshevMap,
+}
+
+impl SequenceAlgorithm {
+    /// Algorithm used for newly written data
+    pub const LATEST: Self = SequenceAlgorithm::WideCatMap;
+
+    /// Every algorithm, in identifier order
+    pub const ALL: [SequenceAlgorithm; 7] = [
+        SequenceAlgorithm::Trigonometric,
+        SequenceAlgorithm::CatMap,
+        SequenceAlgorithm::WideCatMap,
+        SequenceAlgorithm::LogisticMap,
+        SequenceAlgorithm::TentMap,
+        SequenceAlgorithm::HenonMap,
+        SequenceAlgorithm::ChebyshevMap,
+    ];
+
+    /// Stable identifier of the algorithm
+    pub fn id(&self) -> u8 {
+        match self {
+            SequenceAlgorithm::Trigonometric => 1,
+            SequenceAlgorithm::CatMap => 2,
+            SequenceAlgorithm::WideCatMap => 3,
+            SequenceAlgorithm::LogisticMap => 4,
+            SequenceAlgorithm::TentMap => 5,
+            SequenceAlgorithm::HenonMap => 6,
+            SequenceAlgorithm::ChebyshevMap => 7,
+        }
+    }
+
+    /// Look up an algorithm by its identifier
+    pub fn from_id(id: u8) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.id() == id)
+            .ok_or_else(|| Error::Format(format!("Unknown sequence algorithm {}.", id)))
+    }
+
+    /// Lowercase name used on the command line and in CSV output
+    pub fn name(&self) -> &'static str {
+        match self {
+            SequenceAlgorithm::Trigonometric => "trigonometric",
+            SequenceAlgorithm::CatMap => "cat",
+            SequenceAlgorithm::WideCatMap => "wide-cat",
+            SequenceAlgorithm::LogisticMap => "logistic",
+            SequenceAlgorithm::TentMap => "tent",
+            SequenceAlgorithm::HenonMap => "henon",
+            SequenceAlgorithm::ChebyshevMap => "chebyshev",
+        }
+    }
+
+    /// Look up an algorithm by its name
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+            .ok_or_else(|| Error::Format(format!("Unknown chaotic map {}.", name)))
+    }
+
+    /// Generate a chaotic permutation of `0..n` from `seed`
+    pub fn sequence(&self, n: usize, seed: u64) -> Vec<usize> {
+        match self {
+            SequenceAlgorithm::Trigonometric => chaotic_sequence(n, seed),
+            SequenceAlgorithm::CatMap => cat_map_sequence(n, seed),
+            SequenceAlgorithm::WideCatMap => wide_cat_map_sequence(n, seed),
+            SequenceAlgorithm::LogisticMap => map_sequence::<LogisticMap>(n, seed),
+            SequenceAlgorithm::TentMap => map_sequence::<TentMap>(n, seed),
+            SequenceAlgorithm::HenonMap => map_sequence::<HenonMap>(n, seed),
+            SequenceAlgorithm::ChebyshevMap => map_sequence::<ChebyshevMap>(n, seed),
+        }
+    }
+}
+
+/// Compress data using a chaotic sequence.
+pub fn compress(data: &[u8], _chaos_seq: &[usize], value_to_index: &HashMap<u8, usize>) -> Result<Vec<usize>> {
+    data.iter()
+        .map(|&byte| {
+            value_to_index
+                .get(&byte)
+                .copied()
+                .ok_or_else(|| Error::Format(format!("Byte value {} not found in chaos sequence.", byte)))
+        })
+        .collect()
+}
+
+/// Decompress data using a chaotic sequence.
+pub fn decompress(compressed_data: &[usize], chaos_seq: &[usize]) -> Result<Vec<u8>> {
+    compressed_data
+        .iter()
+        .map(|&index| {
+            chaos_seq.get(index).map(|&value| value as u8).ok_or_else(|| {
+                Error::Format(format!("Index {} is out of bounds for the chaotic sequence.", index))
+            })
+        })
+        .collect()
+}
+
+/// Converts a list of integers to a hexadecimal string.
+pub fn list_to_hex(int_list: &[usize]) -> String {
+    int_list.iter().map(|&num| format!("{:02x}", num)).collect()
+}
+
+/// Converts a hexadecimal string back to a list of integers.
+pub fn hex_to_list(hex_string: &str) -> Result<Vec<usize>> {
+    Ok(hex::decode(hex_string)?.into_iter().map(usize::from).collect())
+}
+
+/// Generate a hex-encoded HMAC-SHA3-256 tag.
+pub fn generate_hmac_sha3_256(data: &str, key: &[u8]) -> String {
+    hex::encode(Mac::new(MacAlgorithm::HmacSha3_256, key).compute(data.as_bytes()))
+}
+
+/// Verify a hex-encoded HMAC-SHA3-256 tag in constant time.
+pub fn verify_hmac_sha3_256(data: &str, hmac: &str, key: &[u8]) -> bool {
+    verify_hex_tag(MacAlgorithm::HmacSha3_256, data, hmac, key)
+}
+
+/// Generate a hex-encoded HMAC-SHA3-512 tag.
+pub fn generate_hmac_sha3(data: &str, key: &[u8]) -> String {
+    hex::encode(Mac::new(MacAlgorithm::HmacSha3_512, key).compute(data.as_bytes()))
+}
+
+/// Verify a hex-encoded HMAC-SHA3-512 tag in constant time.
+pub fn verify_hmac_sha3(data: &str, hmac: &str, key: &[u8]) -> bool {
+    verify_hex_tag(MacAlgorithm::HmacSha3_512, data, hmac, key)
+}
+
+/// Decode a hex tag and verify it with the given algorithm
+fn verify_hex_tag(algorithm: MacAlgorithm, data: &str, hmac: &str, key: &[u8]) -> bool {
+    match hex::decode(hmac) {
+        Ok(tag) => Mac::new(algorithm, key).verify(data.as_bytes(), &tag).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Length of the hex-encoded seed prefix produced by `encode_with_seed`
+const SEED_HEX_LENGTH: usize = 16;
+
+/// Length of the hex-encoded HMAC-SHA3-512 suffix produced by `encode_with_seed`
+const HMAC_HEX_LENGTH: usize = 128;
+
+/// Encode data with a given seed and generate HMAC for integrity.
+///
+/// `sequence` selects the chaotic map; data from older releases used
+/// `SequenceAlgorithm::Trigonometric`.
+pub fn encode_with_seed(sequence: SequenceAlgorithm, seed: u64, data: &[u8], hmac_key: &[u8]) -> Result<String> {
+    let chaos_seq = sequence.sequence(256, seed);
+    let value_to_index: HashMap<u8, usize> = chaos_seq
+        .iter()
+        .enumerate()
+        .map(|(idx, &value)| (value as u8, idx))
+        .collect();
+    let compressed_data = compress(data, &chaos_seq, &value_to_index)?;
+    let seed_hex = format!("{:016x}", seed);
+    let data_hex = list_to_hex(&compressed_data);
+    // The tag covers the seed as well, so it cannot be swapped undetected
+    let mac = Mac::new(MacAlgorithm::HmacSha3_512, hmac_key);
+    let hmac = hex::encode(mac.compute_parts(&[seed_hex.as_bytes(), data_hex.as_bytes()]));
+    Ok(format!("{}{}{}", seed_hex, data_hex, hmac))
+}
+
+/// Decode data from an encoded hex string with HMAC verification, using
+/// the `sequence` it was encoded with.
+pub fn decode_with_seed(sequence: SequenceAlgorithm, encoded_str: &str, hmac_key: &[u8]) -> Result<Vec<u8>> {
+    if !encoded_str.is_ascii() {
+        return Err(Error::Format("Encoded string is not hexadecimal.".to_string()));
+    }
+    if encoded_str.len() < SEED_HEX_LENGTH + HMAC_HEX_LENGTH {
+        return Err(Error::Length {
+            expected: SEED_HEX_LENGTH + HMAC_HEX_LENGTH,
+            found: encoded_str.len(),
+        });
+    }
+    let seed_hex = &encoded_str[..SEED_HEX_LENGTH];
+    let data_hex = &encoded_str[SEED_HEX_LENGTH..encoded_str.len() - HMAC_HEX_LENGTH];
+    let hmac = &encoded_str[encoded_str.len() - HMAC_HEX_LENGTH..];
+    let seed_bytes: [u8; 8] = hex::decode(seed_hex)?
+        .try_into()
+        .map_err(|_| Error::Format("Invalid seed length.".to_string()))?;
+    let seed = u64::from_be_bytes(seed_bytes);
+
+    let mac = Mac::new(MacAlgorithm::HmacSha3_512, hmac_key);
+    mac.verify_parts(&[seed_hex.as_bytes(), data_hex.as_bytes()], &hex::decode(hmac)?)?;
+
+    let compressed_data = hex_to_list(data_hex)?;
+    let chaos_seq = sequence.sequence(256, seed);
+    decompress(&compressed_data, &chaos_seq)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `cat_map_sequence(16, 0x0123456789abcdef)`
+    const KAT_16: [usize; 16] = [3, 15, 0, 10, 5, 13, 14, 11, 12, 4, 7, 6, 8, 2, 1, 9];
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let hmac_key = b"chaos hmac key";
+        let data = b"\x00\x01binary\xffdata";
+        let encoded = encode_with_seed(SequenceAlgorithm::Trigonometric, 0x0123_4567_89ab_cdef, data, hmac_key).unwrap();
+        assert_eq!(decode_with_seed(SequenceAlgorithm::Trigonometric, &encoded, hmac_key).unwrap(), data);
+
+        let encoded = encode_with_seed(SequenceAlgorithm::HenonMap, 0x0123_4567_89ab_cdef, data, hmac_key).unwrap();
+        assert_eq!(decode_with_seed(SequenceAlgorithm::HenonMap, &encoded, hmac_key).unwrap(), data);
+        for algorithm in SequenceAlgorithm::ALL {
+            assert_eq!(SequenceAlgorithm::from_id(algorithm.id()).unwrap(), algorithm);
+            assert_eq!(SequenceAlgorithm::from_name(algorithm.name()).unwrap(), algorithm);
+        }
+    }
+
+    #[test]
+    fn test_cat_map_known_answers() {
+        use crate::chaotic_map::cat_map;
+
+        assert_eq!(cat_map(0x0000_0001_0000_0001), 0x0000_0002_0000_0003);
+        assert_eq!(cat_map(0xffff_ffff_0000_0002), 0x0000_0001_0000_0003);
+        assert_eq!(cat_map_sequence(16, 0x0123_4567_89ab_cdef), KAT_16);
+        assert_eq!(SequenceAlgorithm::from_id(2).unwrap().sequence(16, 0x0123_4567_89ab_cdef), KAT_16);
+
+        let mut sorted = cat_map_sequence(1000, 42);
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..1000).collect::<Vec<_>>());
+    }
+
+    /// Number of seed pairs in `0..seeds` that give the same permutation of `0..n`
+    fn colliding_pairs(algorithm: SequenceAlgorithm, n: usize, seeds: u64) -> usize {
+        let mut counts: HashMap<Vec<usize>, usize> = HashMap::new();
+        for seed in 0..seeds {
+            *counts.entry(algorithm.sequence(n, seed.wrapping_mul(0x9e37_79b9_7f4a_7c15))).or_default() += 1;
+        }
+        counts.values().map(|&count| count * (count - 1) / 2).sum()
+    }
+
+    #[test]
+    fn test_wide_state_collision_rate() {
+        // 2000 seeds over the 8! permutations of 8 elements: C(2000, 2) / 40320
+        // is about 49.6 expected colliding pairs, and the legacy generator,
+        // whose state collapses to 0..8, lands on a few dozen permutations
+        let pairs = colliding_pairs(SequenceAlgorithm::WideCatMap, 8, 2000);
+        assert!((15..=85).contains(&pairs), "{} colliding pairs", pairs);
+        assert!(colliding_pairs(SequenceAlgorithm::Trigonometric, 8, 2000) > 10_000);
+
+        // S-Box sized sequences should never collide
+        assert_eq!(colliding_pairs(SequenceAlgorithm::WideCatMap, 256, 200), 0);
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_input() {
+        let hmac_key = b"chaos hmac key";
+        assert!(matches!(decode_with_seed(SequenceAlgorithm::Trigonometric, "abcd", hmac_key), Err(Error::Length { .. })));
+        assert!(matches!(decode_with_seed(SequenceAlgorithm::Trigonometric, &"é".repeat(100), hmac_key), Err(Error::Format(_))));
+
+        let mut encoded = encode_with_seed(SequenceAlgorithm::Trigonometric, 42, b"payload", hmac_key).unwrap();
+        encoded.replace_range(20..22, "00");
+        assert!(matches!(decode_with_seed(SequenceAlgorithm::Trigonometric, &encoded, hmac_key), Err(Error::Authentication)));
+        assert!(matches!(hex_to_list("zz"), Err(Error::Hex(_))));
+        assert!(matches!(decompress(&[300], &chaotic_sequence(256, 1)), Err(Error::Format(_))));
+    }
+}
@@ -0,0 +1,378 @@
+//! Dynamical analysis of the chaotic maps.
+//!
+//! The largest Lyapunov exponent is estimated with the tangent-vector
+//! method: a unit vector is pushed through the Jacobian along the orbit and
+//! the logarithms of its growth are averaged. A positive exponent means
+//! nearby orbits separate exponentially, i.e. the map is chaotic at that
+//! parameter. Bifurcation data records the long-run orbit for a sweep of the
+//! control parameter. Both run the `RealValuedMap` implementations of the
+//! logistic, tent, Hénon and Chebyshev maps. The maps the cipher suites use
+//! (trigonometric, cat and wide cat) are discrete and hash-driven, so they
+//! are measured through their `ChaoticMap` implementations instead: by the
+//! divergence of two orbits from seeds one bit apart, and by the one-bit
+//! seed sensitivity of whole permutations.
+
+use crate::chaos::SequenceAlgorithm;
+use crate::chaotic_map::{CatMap, ChaoticMap, ChebyshevMap, HenonMap, LogisticMap, RealValuedMap, TentMap, TrigonometricMap, WideCatMap};
+use crate::error::{Error, Result};
+
+/// Iterations discarded before measuring, so the orbit settles on its attractor
+pub const TRANSIENT: usize = 1_000;
+
+/// Seed of the orbits measured by `lyapunov_exponent` and `bifurcation`
+const ORBIT_SEED: u64 = 0x5eed;
+
+/// `RealValuedMap::step` of one map
+type Step = fn(f64, [f64; 2]) -> ([f64; 2], [[f64; 2]; 2]);
+
+/// The dynamics of one `RealValuedMap`
+struct Dynamics {
+    parameter: f64,
+    range: (f64, f64),
+    initial_state: [f64; 2],
+    step: Step,
+}
+
+impl Dynamics {
+    fn of<M: RealValuedMap>() -> Self {
+        Self {
+            parameter: M::PARAMETER,
+            range: M::PARAMETER_RANGE,
+            initial_state: M::seed(ORBIT_SEED, 0).state(),
+            step: M::step,
+        }
+    }
+}
+
+/// The real-valued dynamics behind `map`
+fn dynamics(map: SequenceAlgorithm) -> Result<Dynamics> {
+    match map {
+        SequenceAlgorithm::LogisticMap => Ok(Dynamics::of::<LogisticMap>()),
+        SequenceAlgorithm::TentMap => Ok(Dynamics::of::<TentMap>()),
+        SequenceAlgorithm::HenonMap => Ok(Dynamics::of::<HenonMap>()),
+        SequenceAlgorithm::ChebyshevMap => Ok(Dynamics::of::<ChebyshevMap>()),
+        SequenceAlgorithm::Trigonometric | SequenceAlgorithm::CatMap | SequenceAlgorithm::WideCatMap => {
+            Err(Error::Format(format!("The {} map has no real-valued state; measure its divergence instead.", map.name())))
+        }
+    }
+}
+
+/// Parameter the `chaotic_map` implementation of `map` runs at
+pub fn default_parameter(map: SequenceAlgorithm) -> Result<f64> {
+    dynamics(map).map(|dynamics| dynamics.parameter)
+}
+
+/// Default bifurcation and Lyapunov sweep range for `map`
+pub fn default_range(map: SequenceAlgorithm) -> Result<(f64, f64)> {
+    dynamics(map).map(|dynamics| dynamics.range)
+}
+
+/// Estimate the largest Lyapunov exponent of `map` at parameter `p` over
+/// `iterations` steps after the transient.
+///
+/// Returns `NaN` if the orbit diverges.
+pub fn lyapunov_exponent(map: SequenceAlgorithm, p: f64, iterations: usize) -> Result<f64> {
+    let Dynamics { initial_state, step, .. } = dynamics(map)?;
+    let mut state = initial_state;
+    for _ in 0..TRANSIENT {
+        state = step(p, state).0;
+    }
+
+    let mut tangent = [1.0, 1.0];
+    let mut sum = 0.0;
+    for _ in 0..iterations {
+        let (next, [[a, b], [c, d]]) = step(p, state);
+        tangent = [a * tangent[0] + b * tangent[1], c * tangent[0] + d * tangent[1]];
+        let norm = tangent[0].hypot(tangent[1]);
+        if !next[0].is_finite() || !norm.is_finite() {
+            return Ok(f64::NAN);
+        }
+        // A zero derivative (e.g. the logistic peak) would be ln 0; restart the tangent
+        sum += norm.max(f64::MIN_POSITIVE).ln();
+        tangent = if norm > 0.0 { [tangent[0] / norm, tangent[1] / norm] } else { [1.0, 1.0] };
+        state = next;
+    }
+    Ok(sum / iterations as f64)
+}
+
+/// A Lyapunov exponent at one parameter value
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyapunovPoint {
+    pub map: SequenceAlgorithm,
+    pub parameter: f64,
+    pub exponent: f64,
+}
+
+/// One point of an orbit after the transient, at one parameter value
+#[derive(Debug, Clone, PartialEq)]
+pub struct BifurcationPoint {
+    pub map: SequenceAlgorithm,
+    pub parameter: f64,
+    pub x: f64,
+}
+
+/// Reject a zero `value` for the count called `name`
+fn require_nonzero(name: &str, value: usize) -> Result<()> {
+    if value == 0 {
+        return Err(Error::Format(format!("{} must be at least 1", name)));
+    }
+    Ok(())
+}
+
+/// `steps` evenly spaced parameters from `from` to `to` inclusive
+fn sweep(from: f64, to: f64, steps: usize) -> impl Iterator<Item = f64> {
+    (0..steps).map(move |i| if steps == 1 { from } else { from + (to - from) * i as f64 / (steps - 1) as f64 })
+}
+
+/// Lyapunov exponents over a parameter sweep
+pub fn lyapunov_sweep(map: SequenceAlgorithm, from: f64, to: f64, steps: usize, iterations: usize) -> Result<Vec<LyapunovPoint>> {
+    require_nonzero("steps", steps)?;
+    sweep(from, to, steps)
+        .map(|parameter| {
+            Ok(LyapunovPoint {
+                map,
+                parameter,
+                exponent: lyapunov_exponent(map, parameter, iterations)?,
+            })
+        })
+        .collect()
+}
+
+/// Bifurcation diagram data: `samples` orbit points after the transient for
+/// each parameter of the sweep
+pub fn bifurcation(map: SequenceAlgorithm, from: f64, to: f64, steps: usize, samples: usize) -> Result<Vec<BifurcationPoint>> {
+    require_nonzero("steps", steps)?;
+    let Dynamics { initial_state, step, .. } = dynamics(map)?;
+    let mut points = Vec::with_capacity(steps * samples);
+    for parameter in sweep(from, to, steps) {
+        let mut state = initial_state;
+        for _ in 0..TRANSIENT {
+            state = step(parameter, state).0;
+        }
+        for _ in 0..samples {
+            state = step(parameter, state).0;
+            points.push(BifurcationPoint { map, parameter, x: state[0] });
+        }
+    }
+    Ok(points)
+}
+
+/// How far two orbits from seeds one bit apart have separated after one step
+#[derive(Debug, Clone, PartialEq)]
+pub struct DivergencePoint {
+    pub map: SequenceAlgorithm,
+    /// Step of the orbits, from 1
+    pub step: usize,
+    /// Fraction of trials whose output indices differ at this step
+    pub differing: f64,
+    /// Mean distance between the output indices, as a fraction of the length
+    pub mean_distance: f64,
+}
+
+/// Trial seeds, spread over the whole 64-bit range
+fn trial_seeds(trials: u64) -> impl Iterator<Item = u64> {
+    (0..trials).map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ 0x5eed)
+}
+
+/// Per-step `(differing, mean_distance)` of `M` over `trials` orbit pairs
+fn orbit_divergence<M: ChaoticMap>(length: usize, steps: usize, trials: u64) -> Vec<(f64, f64)> {
+    let mut totals = vec![(0usize, 0usize); steps];
+    for (trial, seed) in trial_seeds(trials).enumerate() {
+        let mut a = M::seed(seed, length);
+        let mut b = M::seed(seed ^ (1 << (trial % 64)), length);
+        for (step, total) in totals.iter_mut().enumerate() {
+            a.next_state(step as u64);
+            b.next_state(step as u64);
+            let (x, y) = (a.output_index(0, length), b.output_index(0, length));
+            total.0 += (x != y) as usize;
+            total.1 += x.abs_diff(y);
+        }
+    }
+    let trials = trials.max(1) as f64;
+    totals.into_iter().map(|(differing, distance)| (differing as f64 / trials, distance as f64 / trials / length as f64)).collect()
+}
+
+/// Orbit divergence of `map`, the discrete counterpart of the Lyapunov
+/// exponent.
+///
+/// Each trial steps the map from a seed and from the seed with one bit
+/// flipped (cycling through the 64 bits), comparing the swap indices for a
+/// `length`-element sequence at every step. A chaotic map separates the
+/// orbits at once: about `1 - 1/length` of the indices differ and their mean
+/// distance approaches 1/3 of the length.
+pub fn divergence(map: SequenceAlgorithm, length: usize, steps: usize, trials: u64) -> Result<Vec<DivergencePoint>> {
+    require_nonzero("length", length)?;
+    require_nonzero("steps", steps)?;
+    let per_step = match map {
+        SequenceAlgorithm::Trigonometric => orbit_divergence::<TrigonometricMap>(length, steps, trials),
+        SequenceAlgorithm::CatMap => orbit_divergence::<CatMap>(length, steps, trials),
+        SequenceAlgorithm::WideCatMap => orbit_divergence::<WideCatMap>(length, steps, trials),
+        SequenceAlgorithm::LogisticMap => orbit_divergence::<LogisticMap>(length, steps, trials),
+        SequenceAlgorithm::TentMap => orbit_divergence::<TentMap>(length, steps, trials),
+        SequenceAlgorithm::HenonMap => orbit_divergence::<HenonMap>(length, steps, trials),
+        SequenceAlgorithm::ChebyshevMap => orbit_divergence::<ChebyshevMap>(length, steps, trials),
+    };
+    Ok(per_step
+        .into_iter()
+        .enumerate()
+        .map(|(step, (differing, mean_distance))| DivergencePoint { map, step: step + 1, differing, mean_distance })
+        .collect())
+}
+
+/// How far permutations move when one seed bit flips
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensitivityPoint {
+    pub map: SequenceAlgorithm,
+    /// Seed bit that was flipped
+    pub bit: u32,
+    /// Mean fraction of positions that differ, over all trial seeds
+    pub mean_distance: f64,
+    pub min_distance: f64,
+    pub max_distance: f64,
+}
+
+/// Fraction of positions at which two permutations differ
+pub fn hamming_distance(a: &[usize], b: &[usize]) -> f64 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    a.iter().zip(b).filter(|(x, y)| x != y).count() as f64 / a.len() as f64
+}
+
+/// One-bit seed sensitivity of `map` for `length`-element permutations.
+///
+/// For each of the 64 seed bits, every trial seed is compared with the seed
+/// with that bit flipped. Unrelated random permutations differ in all but
+/// about one position, so a good map scores close to `1 - 1/length`.
+pub fn seed_sensitivity(map: SequenceAlgorithm, length: usize, trials: u64) -> Result<Vec<SensitivityPoint>> {
+    require_nonzero("length", length)?;
+    require_nonzero("trials", trials as usize)?;
+    let seeds: Vec<u64> = trial_seeds(trials).collect();
+    let bases: Vec<Vec<usize>> = seeds.iter().map(|&seed| map.sequence(length, seed)).collect();
+
+    Ok((0..64)
+        .map(|bit| {
+            let distances: Vec<f64> = seeds
+                .iter()
+                .zip(&bases)
+                .map(|(&seed, base)| hamming_distance(base, &map.sequence(length, seed ^ (1 << bit))))
+                .collect();
+            SensitivityPoint {
+                map,
+                bit,
+                mean_distance: distances.iter().sum::<f64>() / distances.len().max(1) as f64,
+                min_distance: distances.iter().copied().fold(f64::INFINITY, f64::min),
+                max_distance: distances.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            }
+        })
+        .collect())
+}
+
+/// A row of CSV output
+pub trait CsvRecord {
+    /// Column names
+    const HEADER: &'static str;
+
+    /// Comma-separated values of this row
+    fn record(&self) -> String;
+}
+
+impl CsvRecord for LyapunovPoint {
+    const HEADER: &'static str = "map,parameter,lyapunov_exponent";
+
+    fn record(&self) -> String {
+        format!("{},{},{}", self.map.name(), self.parameter, self.exponent)
+    }
+}
+
+impl CsvRecord for BifurcationPoint {
+    const HEADER: &'static str = "map,parameter,x";
+
+    fn record(&self) -> String {
+        format!("{},{},{}", self.map.name(), self.parameter, self.x)
+    }
+}
+
+impl CsvRecord for DivergencePoint {
+    const HEADER: &'static str = "map,step,differing,mean_distance";
+
+    fn record(&self) -> String {
+        format!("{},{},{},{}", self.map.name(), self.step, self.differing, self.mean_distance)
+    }
+}
+
+impl CsvRecord for SensitivityPoint {
+    const HEADER: &'static str = "map,bit,mean_distance,min_distance,max_distance";
+
+    fn record(&self) -> String {
+        format!("{},{},{},{},{}", self.map.name(), self.bit, self.mean_distance, self.min_distance, self.max_distance)
+    }
+}
+
+/// Render rows as CSV with a header line
+pub fn to_csv<T: CsvRecord>(rows: &[T]) -> String {
+    let mut csv = String::from(T::HEADER);
+    csv.push('\n');
+    for row in rows {
+        csv.push_str(&row.record());
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lyapunov_exponents_match_theory() {
+        let close = |map, p, expected: f64| {
+            let exponent = lyapunov_exponent(map, p, 100_000).unwrap();
+            assert!((exponent - expected).abs() < 0.02, "{:?} at {}: {} != {}", map, p, exponent, expected);
+        };
+        close(SequenceAlgorithm::LogisticMap, 4.0, 2f64.ln());
+        close(SequenceAlgorithm::TentMap, 1.9, 1.9f64.ln());
+        close(SequenceAlgorithm::ChebyshevMap, 4.0, 4f64.ln());
+        // Published value for the classic Hénon attractor
+        close(SequenceAlgorithm::HenonMap, 1.4, 0.419);
+
+        // Period-2 window of the logistic map
+        assert!(lyapunov_exponent(SequenceAlgorithm::LogisticMap, 3.2, 10_000).unwrap() < 0.0);
+        let orbit = bifurcation(SequenceAlgorithm::LogisticMap, 3.2, 3.2, 1, 50).unwrap();
+        let mut values: Vec<u64> = orbit.iter().map(|point| (point.x * 1e9).round() as u64).collect();
+        values.sort_unstable();
+        values.dedup();
+        assert_eq!(values.len(), 2);
+
+        assert!(lyapunov_exponent(SequenceAlgorithm::WideCatMap, 1.0, 10).is_err());
+        assert!(bifurcation(SequenceAlgorithm::CatMap, 1.0, 1.0, 1, 1).is_err());
+        assert_eq!(default_parameter(SequenceAlgorithm::ChebyshevMap).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn test_cipher_maps_diverge() {
+        for map in [SequenceAlgorithm::Trigonometric, SequenceAlgorithm::CatMap, SequenceAlgorithm::WideCatMap] {
+            let points = divergence(map, 256, 8, 256).unwrap();
+            assert_eq!(points.len(), 8);
+            assert!(points.iter().all(|point| point.differing > 0.95 && (point.mean_distance - 1.0 / 3.0).abs() < 0.06), "{:?}", points);
+        }
+        let csv = to_csv(&divergence(SequenceAlgorithm::Trigonometric, 256, 1, 4).unwrap());
+        assert!(csv.starts_with("map,step,differing,mean_distance\ntrigonometric,1,"));
+
+        // An empty sequence has no indices to compare and an empty sweep no points
+        assert!(matches!(divergence(SequenceAlgorithm::Trigonometric, 0, 8, 4), Err(Error::Format(_))));
+        assert!(matches!(divergence(SequenceAlgorithm::CatMap, 256, 0, 4), Err(Error::Format(_))));
+        assert!(matches!(seed_sensitivity(SequenceAlgorithm::WideCatMap, 0, 4), Err(Error::Format(_))));
+        assert!(matches!(lyapunov_sweep(SequenceAlgorithm::LogisticMap, 3.0, 4.0, 0, 10), Err(Error::Format(_))));
+    }
+
+    #[test]
+    fn test_seed_sensitivity_and_csv() {
+        let points = seed_sensitivity(SequenceAlgorithm::WideCatMap, 64, 4).unwrap();
+        assert_eq!(points.len(), 64);
+        assert!(points.iter().all(|point| point.mean_distance > 0.8));
+
+        let csv = to_csv(&points[..1]);
+        assert!(csv.starts_with("map,bit,mean_distance,min_distance,max_distance\nwide-cat,0,"));
+    }
+}
@@ -0,0 +1,406 @@
+//! Pluggable chaotic maps.
+//!
+//! A `ChaoticMap` is seeded, stepped, and asked for a swap partner at each
+//! position of a Fisher–Yates style shuffle; `map_sequence` turns any map
+//! into a permutation of `0..n`. `SequenceAlgorithm` selects among the maps
+//! here: the ones behind the cipher suites and classic maps (logistic, tent,
+//! Hénon, Chebyshev) for comparing chaos sources. The classic maps use only IEEE 754 arithmetic
+//! (no `libm` calls), so they too are reproducible across platforms.
+
+use sha3::{Digest, Sha3_256};
+
+use crate::chaos::enhanced_perturbation;
+
+/// A chaotic map that drives a permutation shuffle
+pub trait ChaoticMap {
+    /// Initial state for `seed` when generating a `length`-element sequence
+    fn seed(seed: u64, length: usize) -> Self;
+
+    /// Advance the state for step `step`
+    fn next_state(&mut self, step: u64);
+
+    /// Swap partner for `position` of a `length`-element sequence, derived
+    /// from the current state
+    fn output_index(&self, position: usize, length: usize) -> usize;
+}
+
+/// A `ChaoticMap` with a real-valued state and a control parameter.
+///
+/// `next_state` is `step` at `PARAMETER`, so `chaos_analysis` measures the
+/// same dynamics the map runs as a sequence generator.
+pub trait RealValuedMap: ChaoticMap {
+    /// Control parameter the map runs at
+    const PARAMETER: f64;
+
+    /// Default parameter sweep for analysis
+    const PARAMETER_RANGE: (f64, f64);
+
+    /// The current state; one-dimensional maps leave the second coordinate at 0
+    fn state(&self) -> [f64; 2];
+
+    /// Apply the map with control parameter `p` once, returning the new
+    /// state and the Jacobian at `state`
+    fn step(p: f64, state: [f64; 2]) -> ([f64; 2], [[f64; 2]; 2]);
+}
+
+/// Generate a permutation of `0..n` by swapping each position with the
+/// index the map outputs after stepping
+pub fn map_sequence<M: ChaoticMap>(n: usize, seed: u64) -> Vec<usize> {
+    let mut chaos_seq: Vec<usize> = (0..n).collect();
+    let mut map = M::seed(seed, n);
+
+    for i in 0..n {
+        map.next_state(i as u64);
+        chaos_seq.swap(i, map.output_index(i, n));
+    }
+
+    chaos_seq
+}
+
+/// Scale 64 uniform bits into `position..length` by the high half of a 128-bit product
+fn scale_index(bits: u64, position: usize, length: usize) -> usize {
+    position + ((bits as u128 * (length - position) as u128) >> 64) as usize
+}
+
+/// Scale the low 32 mantissa bits of a floating-point state into `position..length`
+fn mantissa_index(x: f64, position: usize, length: usize) -> usize {
+    scale_index(x.to_bits() << 32, position, length)
+}
+
+/// A seed-derived value in the open interval `(0, 1)`
+fn unit_interval(seed: u64, label: &[u8]) -> f64 {
+    let mut hasher = Sha3_256::new();
+    hasher.update(label);
+    hasher.update(seed.to_be_bytes());
+    let hash = hasher.finalize();
+    let bits = u64::from_be_bytes(hash[0..8].try_into().unwrap()) >> 11;
+    (bits as f64 + 0.5) / (1u64 << 53) as f64
+}
+
+/// The original trigonometric map behind `chaotic_sequence`.
+///
+/// Its state is reduced modulo the sequence length, and the swap partner
+/// may be any index, not just one at or after the current position.
+#[derive(Debug, Clone)]
+pub struct TrigonometricMap {
+    state: u64,
+    length: usize,
+    index: usize,
+}
+
+impl ChaoticMap for TrigonometricMap {
+    fn seed(seed: u64, length: usize) -> Self {
+        Self { state: seed, length, index: 0 }
+    }
+
+    fn next_state(&mut self, step: u64) {
+        let n = self.length;
+        let perturbation = enhanced_perturbation(self.state, step);
+        let mod_pi = (self.state % ((std::f64::consts::PI * 1e8) as u64)) as f64 / 1e8;
+        let trig_transform = (mod_pi.sin() * mod_pi.cos()).abs();
+        self.index = ((trig_transform * n as f64) as u64).wrapping_add(perturbation) as usize % n;
+        self.state = self.state.wrapping_add(perturbation) % n as u64;
+    }
+
+    fn output_index(&self, _position: usize, _length: usize) -> usize {
+        self.index
+    }
+}
+
+/// One step of the Arnold cat map `(x, y) -> (x + y, x + 2y)` on `Z_{2^32}^2`,
+/// with the state packed as `x || y`
+pub fn cat_map(state: u64) -> u64 {
+    let (x, y) = ((state >> 32) as u32, state as u32);
+    let x = x.wrapping_add(y);
+    let y = x.wrapping_add(y);
+    ((x as u64) << 32) | y as u64
+}
+
+/// The Arnold cat map on a 64-bit state, each state hashed with its step
+#[derive(Debug, Clone)]
+pub struct CatMap {
+    state: u64,
+    output: u64,
+}
+
+impl ChaoticMap for CatMap {
+    fn seed(seed: u64, _length: usize) -> Self {
+        Self { state: seed, output: 0 }
+    }
+
+    fn next_state(&mut self, step: u64) {
+        self.state = cat_map(self.state);
+        self.output = enhanced_perturbation(self.state, step);
+    }
+
+    fn output_index(&self, position: usize, length: usize) -> usize {
+        scale_index(self.output, position, length)
+    }
+}
+
+/// A 256-bit chaotic state.
+///
+/// The state is two Arnold cat map planes on `Z_{2^64}^2`. Every step
+/// applies the cat map to both planes and folds SHA3-256 of the whole state
+/// and the step counter back into all four lanes, so no part of the state is
+/// ever truncated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WideCatMap {
+    lanes: [u64; 4],
+    output: u64,
+}
+
+impl ChaoticMap for WideCatMap {
+    /// Expand the seed into the full state with SHA3-256
+    fn seed(seed: u64, _length: usize) -> Self {
+        let mut hasher = Sha3_256::new();
+        hasher.update(b"chaos-state");
+        hasher.update(seed.to_be_bytes());
+        Self {
+            lanes: hash_lanes(&hasher.finalize()),
+            output: 0,
+        }
+    }
+
+    fn next_state(&mut self, step: u64) {
+        let [a, b, c, d] = self.lanes;
+        let (a, b) = (a.wrapping_add(b), a.wrapping_add(b).wrapping_add(b));
+        let (c, d) = (c.wrapping_add(d), c.wrapping_add(d).wrapping_add(d));
+
+        let mut hasher = Sha3_256::new();
+        for lane in [a, b, c, d] {
+            hasher.update(lane.to_be_bytes());
+        }
+        hasher.update(step.to_be_bytes());
+        let perturbation = hash_lanes(&hasher.finalize());
+
+        self.lanes = [a ^ perturbation[0], b ^ perturbation[1], c ^ perturbation[2], d ^ perturbation[3]];
+        self.output = perturbation[0];
+    }
+
+    fn output_index(&self, position: usize, length: usize) -> usize {
+        scale_index(self.output, position, length)
+    }
+}
+
+/// Split a 256-bit hash into four big-endian lanes
+fn hash_lanes(hash: &[u8]) -> [u64; 4] {
+    std::array::from_fn(|i| u64::from_be_bytes(hash[8 * i..8 * i + 8].try_into().unwrap()))
+}
+
+/// The logistic map `x -> r x (1 - x)` with `r = 4`
+#[derive(Debug, Clone)]
+pub struct LogisticMap {
+    x: f64,
+}
+
+impl LogisticMap {
+    pub const R: f64 = 4.0;
+}
+
+impl ChaoticMap for LogisticMap {
+    fn seed(seed: u64, _length: usize) -> Self {
+        Self { x: unit_interval(seed, b"logistic") }
+    }
+
+    fn next_state(&mut self, _step: u64) {
+        self.x = Self::step(Self::R, self.state()).0[0];
+    }
+
+    fn output_index(&self, position: usize, length: usize) -> usize {
+        mantissa_index(self.x, position, length)
+    }
+}
+
+impl RealValuedMap for LogisticMap {
+    const PARAMETER: f64 = Self::R;
+    const PARAMETER_RANGE: (f64, f64) = (2.5, 4.0);
+
+    fn state(&self) -> [f64; 2] {
+        [self.x, 0.0]
+    }
+
+    fn step(r: f64, [x, _]: [f64; 2]) -> ([f64; 2], [[f64; 2]; 2]) {
+        ([r * x * (1.0 - x), 0.0], [[r * (1.0 - 2.0 * x), 0.0], [0.0, 0.0]])
+    }
+}
+
+/// The tent map `x -> mu min(x, 1 - x)`.
+///
+/// `mu` sits just below 2: at exactly 2 every step shifts one bit out of
+/// the mantissa and the orbit collapses to 0.
+#[derive(Debug, Clone)]
+pub struct TentMap {
+    x: f64,
+}
+
+impl TentMap {
+    pub const MU: f64 = 1.999_999;
+}
+
+impl ChaoticMap for TentMap {
+    fn seed(seed: u64, _length: usize) -> Self {
+        Self { x: unit_interval(seed, b"tent") }
+    }
+
+    fn next_state(&mut self, _step: u64) {
+        self.x = Self::step(Self::MU, self.state()).0[0];
+    }
+
+    fn output_index(&self, position: usize, length: usize) -> usize {
+        mantissa_index(self.x, position, length)
+    }
+}
+
+impl RealValuedMap for TentMap {
+    const PARAMETER: f64 = Self::MU;
+    const PARAMETER_RANGE: (f64, f64) = (1.0, 2.0);
+
+    fn state(&self) -> [f64; 2] {
+        [self.x, 0.0]
+    }
+
+    fn step(mu: f64, [x, _]: [f64; 2]) -> ([f64; 2], [[f64; 2]; 2]) {
+        let slope = if x < 0.5 { mu } else { -mu };
+        ([mu * x.min(1.0 - x), 0.0], [[slope, 0.0], [0.0, 0.0]])
+    }
+}
+
+/// The Hénon map `(x, y) -> (1 - a x^2 + y, b x)` with the classic `a = 1.4`, `b = 0.3`
+#[derive(Debug, Clone)]
+pub struct HenonMap {
+    x: f64,
+    y: f64,
+}
+
+impl HenonMap {
+    pub const A: f64 = 1.4;
+    pub const B: f64 = 0.3;
+}
+
+impl ChaoticMap for HenonMap {
+    /// Start inside the basin of the attractor, near the origin
+    fn seed(seed: u64, _length: usize) -> Self {
+        Self {
+            x: (unit_interval(seed, b"henon-x") - 0.5) * 0.2,
+            y: (unit_interval(seed, b"henon-y") - 0.5) * 0.2,
+        }
+    }
+
+    fn next_state(&mut self, _step: u64) {
+        [self.x, self.y] = Self::step(Self::A, self.state()).0;
+    }
+
+    fn output_index(&self, position: usize, length: usize) -> usize {
+        mantissa_index(self.x, position, length)
+    }
+}
+
+impl RealValuedMap for HenonMap {
+    const PARAMETER: f64 = Self::A;
+    const PARAMETER_RANGE: (f64, f64) = (1.0, 1.4);
+
+    fn state(&self) -> [f64; 2] {
+        [self.x, self.y]
+    }
+
+    fn step(a: f64, [x, y]: [f64; 2]) -> ([f64; 2], [[f64; 2]; 2]) {
+        ([1.0 - a * x * x + y, Self::B * x], [[-2.0 * a * x, 1.0], [Self::B, 0.0]])
+    }
+}
+
+/// The degree-4 Chebyshev map `x -> T_4(x) = 8x^4 - 8x^2 + 1` on `[-1, 1]`.
+///
+/// As a `RealValuedMap` the parameter is the degree, rounded to an integer.
+#[derive(Debug, Clone)]
+pub struct ChebyshevMap {
+    x: f64,
+}
+
+impl ChebyshevMap {
+    pub const DEGREE: u32 = 4;
+}
+
+impl ChaoticMap for ChebyshevMap {
+    fn seed(seed: u64, _length: usize) -> Self {
+        Self {
+            x: 2.0 * unit_interval(seed, b"chebyshev") - 1.0,
+        }
+    }
+
+    fn next_state(&mut self, _step: u64) {
+        self.x = Self::step(Self::DEGREE as f64, self.state()).0[0];
+    }
+
+    fn output_index(&self, position: usize, length: usize) -> usize {
+        mantissa_index(self.x, position, length)
+    }
+}
+
+impl RealValuedMap for ChebyshevMap {
+    const PARAMETER: f64 = Self::DEGREE as f64;
+    const PARAMETER_RANGE: (f64, f64) = (1.0, 6.0);
+
+    fn state(&self) -> [f64; 2] {
+        [self.x, 0.0]
+    }
+
+    /// Evaluated by the recurrence `T_{k+1} = 2x T_k - T_{k-1}`, without
+    /// `cos`/`acos`, with `T_n' = n U_{n-1}` from the same recurrence for the
+    /// second kind. Rounding can push the state just past ±1, where the map
+    /// diverges, so it is clamped.
+    fn step(degree: f64, [x, _]: [f64; 2]) -> ([f64; 2], [[f64; 2]; 2]) {
+        let degree = degree.round().max(1.0) as u32;
+        let (mut t, mut t_next) = (1.0, x);
+        let (mut u, mut u_next) = (0.0, 1.0);
+        for _ in 1..degree {
+            (t, t_next) = (t_next, 2.0 * x * t_next - t);
+            (u, u_next) = (u_next, 2.0 * x * u_next - u);
+        }
+        let derivative = degree as f64 * u_next;
+        ([t_next.clamp(-1.0, 1.0), 0.0], [[derivative, 0.0], [0.0, 0.0]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_permutation(mut sequence: Vec<usize>) -> bool {
+        let n = sequence.len();
+        sequence.sort_unstable();
+        sequence == (0..n).collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_every_map_yields_distinct_permutations() {
+        fn check<M: ChaoticMap>() {
+            let a = map_sequence::<M>(256, 1);
+            assert!(is_permutation(a.clone()));
+            assert_eq!(a, map_sequence::<M>(256, 1));
+            assert_ne!(a, map_sequence::<M>(256, 2));
+        }
+        check::<TrigonometricMap>();
+        check::<CatMap>();
+        check::<WideCatMap>();
+        check::<LogisticMap>();
+        check::<TentMap>();
+        check::<HenonMap>();
+        check::<ChebyshevMap>();
+    }
+
+    #[test]
+    fn test_classic_maps_stay_on_their_attractors() {
+        let mut henon = HenonMap::seed(7, 0);
+        let mut chebyshev = ChebyshevMap::seed(7, 0);
+        let mut tent = TentMap::seed(7, 0);
+        for step in 0..100_000 {
+            henon.next_state(step);
+            chebyshev.next_state(step);
+            tent.next_state(step);
+        }
+        assert!(henon.x.abs() < 1.5 && henon.y.abs() < 0.5);
+        assert!((-1.0..=1.0).contains(&chebyshev.x));
+        assert!(tent.x > 0.0 && tent.x < 1.0);
+    }
+}
@@ -1,10 +1,18 @@
-use crate::chaos_hmac::*;
-use crate::hyper_prime::*;
-use crate::ortho::*;
+use crate::chaos::{decode_with_seed, encode_with_seed, SequenceAlgorithm};
+use crate::error::{Error, Result};
 use chacha20::cipher::{NewCipher, StreamCipher};
 use chacha20::ChaCha20;
-use num_bigint::BigUint;
-use sha3::{Digest, Sha3_256};
+
+/// ChaCha20 key length in bytes
+const KEY_LENGTH: usize = 32;
+
+/// Build a ChaCha20 instance, rejecting keys of the wrong length.
+fn new_chacha20(key: &[u8], nonce: &[u8; 12]) -> Result<ChaCha20> {
+    ChaCha20::new_from_slices(key, nonce).map_err(|_| Error::Length {
+        expected: KEY_LENGTH,
+        found: key.len(),
+    })
+}
 
 /// Encrypt data using the S-Box and ChaCha20 cipher.
 pub fn encrypt_with_sbox(
@@ -12,15 +20,15 @@ pub fn encrypt_with_sbox(
     sbox: &[u8; 256],
     key: &[u8],
     nonce: &[u8; 12],
-) -> Vec<u8> {
+) -> Result<Vec<u8>> {
     let mut substituted_data = data
         .iter()
         .map(|&byte| sbox[byte as usize])
         .collect::<Vec<u8>>();
 
-    let mut cipher = ChaCha20::new(key.into(), nonce.into());
+    let mut cipher = new_chacha20(key, nonce)?;
     cipher.apply_keystream(&mut substituted_data);
-    substituted_data
+    Ok(substituted_data)
 }
 
 /// Decrypt data using the inverse S-Box and ChaCha20 cipher.
@@ -29,16 +37,16 @@ pub fn decrypt_with_sbox(
     inverse_sbox: &[u8; 256],
     key: &[u8],
     nonce: &[u8; 12],
-) -> Vec<u8> {
+) -> Result<Vec<u8>> {
     let mut decrypted_data = encrypted_data.to_vec();
 
-    let mut cipher = ChaCha20::new(key.into(), nonce.into());
+    let mut cipher = new_chacha20(key, nonce)?;
     cipher.apply_keystream(&mut decrypted_data);
 
-    decrypted_data
+    Ok(decrypted_data
         .iter()
         .map(|&byte| inverse_sbox[byte as usize])
-        .collect()
+        .collect())
 }
 
 /// Full pipeline: Chaos  Lattice  S-Box  Encrypt  Chaos
@@ -49,41 +57,41 @@ pub fn encrypt_pipeline(
     sbox: &[u8; 256],
     key: &[u8],
     nonce: &[u8; 12],
-) -> String {
+) -> Result<String> {
     // Chaos Encoding
-    let chaos_encoded = encode_with_seed(seed, data, hmac_key);
+    let chaos_encoded = encode_with_seed(SequenceAlgorithm::Trigonometric, seed, data, hmac_key)?;
 
     // Encrypt with S-Box and ChaCha20
-    let encrypted_data = encrypt_with_sbox(chaos_encoded.as_bytes(), sbox, key, nonce);
+    let encrypted_data = encrypt_with_sbox(chaos_encoded.as_bytes(), sbox, key, nonce)?;
 
     // Final Chaos Encoding
-    let final_encoded = encode_with_seed(seed, &encrypted_data, hmac_key);
-    final_encoded
+    encode_with_seed(SequenceAlgorithm::Trigonometric, seed, &encrypted_data, hmac_key)
 }
 
 /// Full pipeline: Chaos  Decrypt  S-Box  Lattice  Chaos
 pub fn decrypt_pipeline(
     encrypted_data: &str,
-    seed: u64,
+    _seed: u64,
     hmac_key: &[u8],
     inverse_sbox: &[u8; 256],
     key: &[u8],
     nonce: &[u8; 12],
-) -> Vec<u8> {
+) -> Result<Vec<u8>> {
     // Decode Chaos
-    let chaos_decoded = decode_with_seed(encrypted_data, hmac_key);
+    let chaos_decoded = decode_with_seed(SequenceAlgorithm::Trigonometric, encrypted_data, hmac_key)?;
 
     // Decrypt with S-Box and ChaCha20
-    let decrypted_data = decrypt_with_sbox(&chaos_decoded, inverse_sbox, key, nonce);
+    let decrypted_data = decrypt_with_sbox(&chaos_decoded, inverse_sbox, key, nonce)?;
 
     // Decode Final Chaos
-    decode_with_seed(&String::from_utf8(decrypted_data).unwrap(), hmac_key)
+    let chaos_encoded = String::from_utf8(decrypted_data)
+        .map_err(|_| Error::Format("Decrypted chaos encoding is not valid UTF-8.".to_string()))?;
+    decode_with_seed(SequenceAlgorithm::Trigonometric, &chaos_encoded, hmac_key)
 }
 
 /// Generate a random nonce.
-pub fn generate_nonce() -> [u8; 12] {
+pub fn generate_nonce() -> Result<[u8; 12]> {
     let mut nonce = [0u8; 12];
-    getrandom::getrandom(&mut nonce).expect("Failed to generate random nonce");
-    nonce
+    getrandom::getrandom(&mut nonce).map_err(|e| Error::Random(e.to_string()))?;
+    Ok(nonce)
 }
-
@@ -0,0 +1,434 @@
+//! Self-describing ciphertext container.
+//!
+//! The binary form is
+//! `magic || version || kdf || cipher || mac || nonce_len || nonce || [enc_len || enc] || ct_len || ciphertext || tag`
+//! where `ct_len` is a big-endian `u64` and the tag, whose length follows
+//! from the MAC identifier, covers every byte before it. The KEM
+//! encapsulation `enc`, with a big-endian `u16` length, is only present for
+//! KDFs that carry one (`KdfId::LweKem`). The armored form is
+//! the hex encoding of the binary form inside `CHAOS CIPHERTEXT` markers.
+//!
+//! Files written before the container existed (`CIPHERTEXT` markers around
+//! `hex(ciphertext) || hex(tag)`, with the tag `SHA3-256(key || hex(ciphertext))`)
+//! are still parsed, as version 0.
+
+use crate::chaos::SequenceAlgorithm;
+use crate::lattice::KeySchedule;
+use crate::error::{Error, Result};
+use crate::mac::{constant_time_eq, legacy_tag, Mac, MacAlgorithm};
+use crate::nonce::Nonce;
+use crate::pem::{unwrap_pem_format, wrap_in_pem_format};
+
+/// Magic bytes at the start of every binary container
+pub const CONTAINER_MAGIC: &[u8; 4] = b"MCLC";
+
+/// Container format version written by this release
+pub const CONTAINER_VERSION: u8 = 1;
+
+/// Version reported for pre-container armored ciphertexts
+pub const LEGACY_VERSION: u8 = 0;
+
+/// Armor label of the container
+pub const ARMOR_LABEL: &str = "CHAOS CIPHERTEXT";
+
+/// Armor label of pre-container ciphertexts
+pub const LEGACY_ARMOR_LABEL: &str = "CIPHERTEXT";
+
+const FIXED_HEADER_LENGTH: usize = 4 + 1 + 1 + 1 + 1 + 1;
+
+/// How the cipher keys are derived from the chaos key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KdfId {
+    /// `Lattice::from_chaos_key` over the key file's seed and HMAC key
+    ChaosKey,
+    /// Keys expanded from an LWE KEM shared secret (see the `hybrid` module)
+    LweKem,
+}
+
+impl KdfId {
+    /// Stable identifier used in the container header
+    pub fn id(&self) -> u8 {
+        match self {
+            KdfId::ChaosKey => 1,
+            KdfId::LweKem => 2,
+        }
+    }
+
+    /// Whether the header carries a KEM encapsulation
+    pub fn has_encapsulation(&self) -> bool {
+        matches!(self, KdfId::LweKem)
+    }
+
+    /// Look up a KDF by its identifier
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(KdfId::ChaosKey),
+            2 => Ok(KdfId::LweKem),
+            _ => Err(Error::Format(format!("Unknown KDF {}.", id))),
+        }
+    }
+}
+
+/// Which cipher produced the ciphertext
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// `Lattice::encrypt`: chaotic keystream followed by the lattice S-Box
+    LatticeSbox,
+    /// `Lattice::encrypt_with_nonce`: keystream and S-Box both mixed with the container nonce
+    LatticeSboxNonce,
+    /// `LatticeSboxNonce` driven by the integer-only `SequenceAlgorithm::CatMap`
+    LatticeSboxNonceCatMap,
+    /// `LatticeSboxNonce` driven by the 256-bit state `SequenceAlgorithm::WideCatMap`
+    LatticeSboxNonceWideCatMap,
+    /// `LatticeSboxNonceWideCatMap` with the lattice bound by the constant-time ladder
+    LatticeSboxNonceConstantTime,
+}
+
+impl CipherSuite {
+    /// Suite used for newly written data
+    pub const LATEST: Self = CipherSuite::LatticeSboxNonceConstantTime;
+
+    /// Stable identifier used in the container header
+    pub fn id(&self) -> u8 {
+        match self {
+            CipherSuite::LatticeSbox => 1,
+            CipherSuite::LatticeSboxNonce => 2,
+            CipherSuite::LatticeSboxNonceCatMap => 3,
+            CipherSuite::LatticeSboxNonceWideCatMap => 4,
+            CipherSuite::LatticeSboxNonceConstantTime => 5,
+        }
+    }
+
+    /// Look up a cipher suite by its identifier
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(CipherSuite::LatticeSbox),
+            2 => Ok(CipherSuite::LatticeSboxNonce),
+            3 => Ok(CipherSuite::LatticeSboxNonceCatMap),
+            4 => Ok(CipherSuite::LatticeSboxNonceWideCatMap),
+            5 => Ok(CipherSuite::LatticeSboxNonceConstantTime),
+            _ => Err(Error::Format(format!("Unknown cipher suite {}.", id))),
+        }
+    }
+
+    /// Key schedule the lattice must be built with
+    pub fn key_schedule(&self) -> KeySchedule {
+        match self {
+            CipherSuite::LatticeSbox | CipherSuite::LatticeSboxNonce => KeySchedule::legacy(SequenceAlgorithm::Trigonometric),
+            CipherSuite::LatticeSboxNonceCatMap => KeySchedule::legacy(SequenceAlgorithm::CatMap),
+            CipherSuite::LatticeSboxNonceWideCatMap => KeySchedule::legacy(SequenceAlgorithm::WideCatMap),
+            CipherSuite::LatticeSboxNonceConstantTime => KeySchedule::LATEST,
+        }
+    }
+}
+
+/// A parsed or freshly sealed ciphertext container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub version: u8,
+    pub kdf: KdfId,
+    pub cipher: CipherSuite,
+    pub mac: MacAlgorithm,
+    pub nonce: Vec<u8>,
+    /// KEM ciphertext the keys are decapsulated from, empty for `KdfId::ChaosKey`
+    pub encapsulation: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+impl Container {
+    /// Build a current-version container and authenticate it with `mac_key`
+    pub fn seal(kdf: KdfId, cipher: CipherSuite, mac: MacAlgorithm, mac_key: &[u8], nonce: Nonce, ciphertext: Vec<u8>) -> Result<Self> {
+        Self::seal_with_encapsulation(kdf, cipher, mac, mac_key, nonce, Vec::new(), ciphertext)
+    }
+
+    /// Like `seal`, with the KEM encapsulation that `kdf` needs
+    pub fn seal_with_encapsulation(kdf: KdfId, cipher: CipherSuite, mac: MacAlgorithm, mac_key: &[u8], nonce: Nonce, encapsulation: Vec<u8>, ciphertext: Vec<u8>) -> Result<Self> {
+        if encapsulation.len() > u16::MAX as usize || kdf.has_encapsulation() == encapsulation.is_empty() {
+            return Err(Error::Format(format!("KDF {} does not take a {}-byte encapsulation.", kdf.id(), encapsulation.len())));
+        }
+
+        let mut container = Self {
+            version: CONTAINER_VERSION,
+            kdf,
+            cipher,
+            mac,
+            nonce: nonce.as_bytes().to_vec(),
+            encapsulation,
+            ciphertext,
+            tag: Vec::new(),
+        };
+        container.tag = Mac::new(mac, mac_key).compute_parts(&[&container.header(), &container.ciphertext]);
+        Ok(container)
+    }
+
+    /// Verify the tag in constant time
+    pub fn verify(&self, mac_key: &[u8]) -> Result<()> {
+        if self.version == LEGACY_VERSION {
+            // Legacy tags are a prefix hash over the hex of the ciphertext alone
+            let expected = legacy_tag(mac_key, hex::encode(&self.ciphertext).as_bytes());
+            return if constant_time_eq(&expected, &self.tag) { Ok(()) } else { Err(Error::Authentication) };
+        }
+        Mac::new(self.mac, mac_key).verify_parts(&[&self.header(), &self.ciphertext], &self.tag)
+    }
+
+    /// Encode everything that precedes the ciphertext
+    fn header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(FIXED_HEADER_LENGTH + self.nonce.len() + 8);
+        header.extend_from_slice(CONTAINER_MAGIC);
+        header.push(self.version);
+        header.push(self.kdf.id());
+        header.push(self.cipher.id());
+        header.push(self.mac.id());
+        header.push(self.nonce.len() as u8);
+        header.extend_from_slice(&self.nonce);
+        if self.kdf.has_encapsulation() {
+            header.extend_from_slice(&(self.encapsulation.len() as u16).to_be_bytes());
+            header.extend_from_slice(&self.encapsulation);
+        }
+        header.extend_from_slice(&(self.ciphertext.len() as u64).to_be_bytes());
+        header
+    }
+
+    /// Serialize to the binary form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header();
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes.extend_from_slice(&self.tag);
+        bytes
+    }
+
+    /// Parse the binary form
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < FIXED_HEADER_LENGTH {
+            return Err(Error::Length {
+                expected: FIXED_HEADER_LENGTH,
+                found: bytes.len(),
+            });
+        }
+        if &bytes[0..4] != CONTAINER_MAGIC {
+            return Err(Error::Format("Missing container magic bytes.".to_string()));
+        }
+        let version = bytes[4];
+        if version != CONTAINER_VERSION {
+            return Err(Error::Format(format!("Unsupported container version {}.", version)));
+        }
+        let kdf = KdfId::from_id(bytes[5])?;
+        let cipher = CipherSuite::from_id(bytes[6])?;
+        let mac = MacAlgorithm::from_id(bytes[7])?;
+        let nonce_length = bytes[8] as usize;
+
+        let rest = &bytes[FIXED_HEADER_LENGTH..];
+        if rest.len() < nonce_length + 8 {
+            return Err(Error::Length {
+                expected: FIXED_HEADER_LENGTH + nonce_length + 8,
+                found: bytes.len(),
+            });
+        }
+        let (nonce, mut rest) = rest.split_at(nonce_length);
+        let mut encapsulation = Vec::new();
+        if kdf.has_encapsulation() {
+            let length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+            if rest.len() < 2 + length + 8 {
+                return Err(Error::Length {
+                    expected: bytes.len() - rest.len() + 2 + length + 8,
+                    found: bytes.len(),
+                });
+            }
+            encapsulation = rest[2..2 + length].to_vec();
+            rest = &rest[2 + length..];
+        }
+        let (ciphertext_length, rest) = rest.split_at(8);
+        let ciphertext_length = u64::from_be_bytes(ciphertext_length.try_into().unwrap());
+
+        let expected = ciphertext_length
+            .checked_add(mac.tag_length() as u64)
+            .ok_or_else(|| Error::Format("Ciphertext length overflowed.".to_string()))?;
+        if rest.len() as u64 != expected {
+            return Err(Error::Length {
+                expected: (bytes.len() - rest.len()).saturating_add(expected as usize),
+                found: bytes.len(),
+            });
+        }
+        let (ciphertext, tag) = rest.split_at(ciphertext_length as usize);
+
+        Ok(Self {
+            version,
+            kdf,
+            cipher,
+            mac,
+            nonce: nonce.to_vec(),
+            encapsulation,
+            ciphertext: ciphertext.to_vec(),
+            tag: tag.to_vec(),
+        })
+    }
+
+    /// Serialize to the armored form
+    pub fn to_armored(&self) -> String {
+        wrap_in_pem_format(ARMOR_LABEL, &hex::encode(self.to_bytes()))
+    }
+
+    /// Parse the armored form, including pre-container `CIPHERTEXT` files
+    pub fn from_armored(content: &str) -> Result<Self> {
+        if let Ok(encoded) = unwrap_pem_format(ARMOR_LABEL, content) {
+            return Self::from_bytes(&hex::decode(encoded)?);
+        }
+
+        let encoded = unwrap_pem_format(LEGACY_ARMOR_LABEL, content)?;
+        let mut ciphertext = hex::decode(encoded)?;
+        let mac = MacAlgorithm::HmacSha3_256;
+        if ciphertext.len() < mac.tag_length() {
+            return Err(Error::Length {
+                expected: mac.tag_length(),
+                found: ciphertext.len(),
+            });
+        }
+        let tag = ciphertext.split_off(ciphertext.len() - mac.tag_length());
+
+        Ok(Self {
+            version: LEGACY_VERSION,
+            kdf: KdfId::ChaosKey,
+            cipher: CipherSuite::LatticeSbox,
+            mac,
+            nonce: Vec::new(),
+            encapsulation: Vec::new(),
+            ciphertext,
+            tag,
+        })
+    }
+
+    /// Parse either form, telling them apart by the magic bytes
+    pub fn parse(input: &[u8]) -> Result<Self> {
+        if input.starts_with(CONTAINER_MAGIC) {
+            return Self::from_bytes(input);
+        }
+        let content = std::str::from_utf8(input).map_err(|_| Error::Format("Ciphertext is neither binary nor armored.".to_string()))?;
+        Self::from_armored(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid::{open_with_identity, seal_for_recipient};
+    use crate::hyper_prime::small_prime_sieve;
+    use crate::lattice::Lattice;
+    use crate::lwe::LweKeyPair;
+    use crate::nonce::{NonceGenerator, NONCE_LENGTH};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn nonce() -> Nonce {
+        NonceGenerator::deterministic(7).next_nonce().unwrap()
+    }
+
+    fn build_lattice(seed: u64, key: &[u8], schedule: KeySchedule) -> Result<Lattice> {
+        Lattice::from_chaos_key_with_schedule(schedule, seed, key, 4, 1, 32, &small_prime_sieve(1_000), 10)
+    }
+
+    /// A hybrid container for the identity with chaos seed 7
+    fn sealed_for_recipient(plaintext: &[u8]) -> (LweKeyPair, Container) {
+        let identity = LweKeyPair::from_chaos_seed(7, &[0x5a; 32]);
+        let container = seal_for_recipient(&identity.public, plaintext, &mut ChaCha20Rng::seed_from_u64(25), &mut NonceGenerator::deterministic(1), build_lattice).unwrap();
+        (identity, container)
+    }
+
+    fn sealed() -> Container {
+        Container::seal(KdfId::ChaosKey, CipherSuite::LatticeSboxNonce, MacAlgorithm::HmacSha3_256, b"mac key", nonce(), b"ciphertext".to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_binary_and_armored_roundtrip() {
+        let container = sealed();
+        let bytes = container.to_bytes();
+        assert_eq!(Container::parse(&bytes).unwrap(), container);
+        assert_eq!(Container::parse(container.to_armored().as_bytes()).unwrap(), container);
+        assert!(container.verify(b"mac key").is_ok());
+        assert!(matches!(container.verify(b"other key"), Err(Error::Authentication)));
+
+        // The header is authenticated too
+        let mut tampered = bytes.clone();
+        tampered[FIXED_HEADER_LENGTH] ^= 1;
+        assert!(matches!(Container::parse(&tampered).unwrap().verify(b"mac key"), Err(Error::Authentication)));
+
+        assert!(matches!(Container::parse(&bytes[..bytes.len() - 1]), Err(Error::Length { .. })));
+        let mut unknown = bytes;
+        unknown[6] = 0xff;
+        assert!(matches!(Container::parse(&unknown), Err(Error::Format(_))));
+    }
+
+    #[test]
+    fn test_encapsulation_is_authenticated() {
+        let container = Container::seal_with_encapsulation(KdfId::LweKem, CipherSuite::LatticeSboxNonceWideCatMap, MacAlgorithm::HmacSha3_256, b"mac key", nonce(), vec![9; 300], b"ciphertext".to_vec()).unwrap();
+        let bytes = container.to_bytes();
+        assert_eq!(Container::parse(&bytes).unwrap(), container);
+        assert!(container.verify(b"mac key").is_ok());
+
+        let mut tampered = bytes.clone();
+        tampered[FIXED_HEADER_LENGTH + 16 + 2] ^= 1;
+        assert!(matches!(Container::parse(&tampered).unwrap().verify(b"mac key"), Err(Error::Authentication)));
+        assert!(matches!(Container::parse(&bytes[..FIXED_HEADER_LENGTH + 16 + 100]), Err(Error::Length { .. })));
+
+        assert!(Container::seal(KdfId::LweKem, CipherSuite::LatticeSboxNonce, MacAlgorithm::HmacSha3_256, b"mac key", nonce(), Vec::new()).is_err());
+        assert!(Container::seal_with_encapsulation(KdfId::ChaosKey, CipherSuite::LatticeSboxNonce, MacAlgorithm::HmacSha3_256, b"mac key", nonce(), vec![1], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_seal_for_recipient_roundtrip() {
+        let (identity, container) = sealed_for_recipient(b"hybrid plaintext");
+        assert_eq!((container.kdf, container.cipher), (KdfId::LweKem, CipherSuite::LATEST));
+        let parsed = Container::parse(&container.to_bytes()).unwrap();
+        assert_eq!(open_with_identity(&identity, &parsed, build_lattice).unwrap(), b"hybrid plaintext");
+
+        let stranger = LweKeyPair::from_chaos_seed(8, &[0x5a; 32]);
+        assert!(matches!(open_with_identity(&stranger, &parsed, build_lattice), Err(Error::Authentication)));
+    }
+
+    #[test]
+    fn test_tampered_encapsulation_fails_verify() {
+        let (identity, container) = sealed_for_recipient(b"hybrid plaintext");
+        let keys = crate::hybrid::decapsulate(&identity, &container.encapsulation).unwrap();
+        assert!(container.verify(&keys.mac_key).is_ok());
+
+        // Tampering changes the decapsulated keys, and the header tag covers the encapsulation bytes as well
+        let mut tampered = container.to_bytes();
+        tampered[FIXED_HEADER_LENGTH + NONCE_LENGTH + 2 + 10] ^= 1;
+        let tampered = Container::parse(&tampered).unwrap();
+        assert!(matches!(tampered.verify(&keys.mac_key), Err(Error::Authentication)));
+        let tampered_keys = crate::hybrid::decapsulate(&identity, &tampered.encapsulation).unwrap();
+        assert!(matches!(tampered.verify(&tampered_keys.mac_key), Err(Error::Authentication)));
+        assert!(matches!(open_with_identity(&identity, &tampered, build_lattice), Err(Error::Authentication)));
+    }
+
+    #[test]
+    fn test_chaos_key_container_does_not_open_as_hybrid() {
+        // The identity's chaos key seals a chaos-key container, which has no encapsulation to decapsulate
+        let identity = LweKeyPair::from_chaos_seed(7, &[0x5a; 32]);
+        let lattice = build_lattice(7, &[0x5a; 32], KeySchedule::LATEST).unwrap();
+        let (nonce, ciphertext) = lattice.encrypt_with_nonce(b"chaos plaintext", 7, &mut NonceGenerator::deterministic(1)).unwrap();
+        let container = Container::seal(KdfId::ChaosKey, CipherSuite::LATEST, MacAlgorithm::HmacSha3_256, &[0x5a; 32], nonce, ciphertext).unwrap();
+        assert!(matches!(open_with_identity(&identity, &container, build_lattice), Err(Error::Format(_))));
+
+        // Relabelling it as hybrid breaks both its tag and its layout
+        let mut relabelled = container.to_bytes();
+        relabelled[5] = KdfId::LweKem.id();
+        assert!(Container::parse(&relabelled).map_or(true, |parsed| open_with_identity(&identity, &parsed, build_lattice).is_err()));
+    }
+
+    #[test]
+    fn test_parses_legacy_armor() {
+        // Made by the first release with `encrypt 64`; the key's HMAC key prime is 0x6846c3b880090c91
+        let armored = "--- BEGIN CIPHERTEXT ---\n\
+                       810b0b268855cc9d6e71556c0b4bb46bdf882b6a0ba70b802b24b0a8b694eb64\n\
+                       3549eab1851d6541c87c0e819e2c0bcc193ac79080abaa9108\n\
+                       --- END CIPHERTEXT ---";
+
+        let container = Container::parse(armored.as_bytes()).unwrap();
+        assert_eq!(container.version, LEGACY_VERSION);
+        assert_eq!(container.cipher, CipherSuite::LatticeSbox);
+        assert_eq!(hex::encode(&container.ciphertext), "810b0b268855cc9d6e71556c0b4bb46bdf882b6a0ba70b802b");
+        assert!(container.verify(&hex::decode("6846c3b880090c91").unwrap()).is_ok());
+        assert!(matches!(container.verify(b"mac key"), Err(Error::Authentication)));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+/// Errors returned by the public API of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// Input is not in the expected encoding or layout
+    Format(String),
+    /// Input has the wrong length
+    Length { expected: usize, found: usize },
+    /// HMAC verification failed
+    Authentication,
+    /// Invalid hexadecimal encoding
+    Hex(hex::FromHexError),
+    /// Reading or writing a file failed
+    Io(std::io::Error),
+    /// Prime generation parameters are invalid or the search was exhausted
+    PrimeGeneration(String),
+    /// Lattice parameters or arithmetic are invalid
+    Lattice(String),
+    /// The operating system random number generator failed
+    Random(String),
+    /// A nonce was used for more than one message
+    NonceReuse,
+    /// The key is passphrase-protected and no passphrase was given
+    PassphraseRequired,
+    /// An S-Box failed a quality threshold
+    WeakSbox(String),
+}
+
+/// Result type used throughout this crate
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Format(msg) => write!(f, "Invalid format: {}", msg),
+            Error::Length { expected, found } => {
+                write!(f, "Length mismatch. Expected {}, found {}.", expected, found)
+            }
+            Error::Authentication => {
+                write!(f, "HMAC verification failed. The data may have been tampered with.")
+            }
+            Error::Hex(e) => write!(f, "Invalid hex encoding: {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::PrimeGeneration(msg) => write!(f, "Prime generation failed: {}", msg),
+            Error::Lattice(msg) => write!(f, "Lattice error: {}", msg),
+            Error::Random(msg) => write!(f, "Random number generation failed: {}", msg),
+            Error::NonceReuse => write!(f, "Nonce reuse detected. Every message needs a fresh nonce."),
+            Error::WeakSbox(msg) => write!(f, "Weak S-Box: {}", msg),
+            Error::PassphraseRequired => write!(f, "The key is passphrase-protected. Supply a passphrase to unlock it."),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Hex(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(e: hex::FromHexError) -> Self {
+        Error::Hex(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            other => std::io::Error::new(std::io::ErrorKind::InvalidData, other),
+        }
+    }
+}
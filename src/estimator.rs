@@ -0,0 +1,242 @@
+//! Rough lattice security estimates.
+//!
+//! The basis is LLL-reduced for real to measure its root Hermite factor.
+//! BKZ-β is predicted rather than run: small block sizes use experimental
+//! root Hermite factors, larger ones the Chen–Nguyen asymptotic
+//! `((β / 2πe) (πβ)^(1/β))^(1/(2(β-1)))`. The shortest vector length is
+//! taken from the Gaussian heuristic, and the lattice is considered broken
+//! by the smallest β whose predicted first vector reaches it. That β is
+//! priced with the core-SVP sieving model, `0.292 β` classical and
+//! `0.265 β` quantum bits, which ignores the number of SVP calls and
+//! memory, so treat the result as an order of magnitude. Below
+//! `MIN_ESTIMATE_RANK` the model does not apply and no cost is given.
+
+use std::f64::consts::{E, PI};
+use std::fmt;
+
+use crate::basis::{LatticeBasis, DEFAULT_DELTA};
+use crate::error::Result;
+use crate::lattice::Lattice;
+use crate::stats::ln_gamma;
+
+/// Root Hermite factor LLL reaches in practice
+pub const LLL_ROOT_HERMITE_FACTOR: f64 = 1.0219;
+
+/// Experimental root Hermite factors for small BKZ block sizes
+const SMALL_BLOCK_SIZES: [(usize, f64); 8] = [
+    (2, LLL_ROOT_HERMITE_FACTOR),
+    (5, 1.01862),
+    (10, 1.01616),
+    (15, 1.01485),
+    (20, 1.01420),
+    (25, 1.01342),
+    (28, 1.01331),
+    (40, 1.01295),
+];
+
+/// Classical core-SVP cost exponent of sieving, in bits per block size
+pub const CLASSICAL_SIEVE_EXPONENT: f64 = 0.292;
+
+/// Quantum core-SVP cost exponent of sieving, in bits per block size
+pub const QUANTUM_SIEVE_EXPONENT: f64 = 0.265;
+
+/// Block sizes reported when none are given (those above the rank are skipped)
+pub const DEFAULT_BLOCK_SIZES: [usize; 6] = [10, 20, 40, 60, 80, 100];
+
+/// Smallest rank priced with core-SVP; smaller lattices are solved outright
+/// by enumeration and the BKZ predictions are meaningless for them
+pub const MIN_ESTIMATE_RANK: usize = DEFAULT_BLOCK_SIZES[0];
+
+/// Predicted BKZ-β root Hermite factor
+pub fn bkz_root_hermite_factor(block_size: usize) -> f64 {
+    let (last, _) = SMALL_BLOCK_SIZES[SMALL_BLOCK_SIZES.len() - 1];
+    if block_size <= last {
+        // Interpolate linearly between the measured block sizes
+        return SMALL_BLOCK_SIZES
+            .windows(2)
+            .find(|pair| block_size <= pair[1].0)
+            .map_or(LLL_ROOT_HERMITE_FACTOR, |pair| {
+                let ((b0, d0), (b1, d1)) = (pair[0], pair[1]);
+                let t = (block_size.max(b0) - b0) as f64 / (b1 - b0) as f64;
+                d0 + (d1 - d0) * t
+            });
+    }
+    let beta = block_size as f64;
+    (beta / (2.0 * PI * E) * (PI * beta).powf(1.0 / beta)).powf(1.0 / (2.0 * (beta - 1.0)))
+}
+
+/// Base-2 logarithm of the Gaussian heuristic for the shortest vector of a
+/// rank-`n` lattice, `Γ(n/2 + 1)^(1/n) / sqrt(π) * covolume^(1/n)`
+pub fn log2_gaussian_heuristic(rank: usize, log2_covolume: f64) -> f64 {
+    let n = rank as f64;
+    (ln_gamma(n / 2.0 + 1.0) / n - 0.5 * PI.ln()) / 2f64.ln() + log2_covolume / n
+}
+
+/// Predicted outcome of BKZ with one block size
+#[derive(Debug, Clone, PartialEq)]
+pub struct BkzPrediction {
+    pub block_size: usize,
+    pub root_hermite_factor: f64,
+    /// Base-2 logarithm of the predicted first basis vector length
+    pub log2_shortest: f64,
+}
+
+/// Core-SVP cost of the smallest block size predicted to reach the Gaussian heuristic
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoreSvpCost {
+    pub block_size: usize,
+    pub classical_bits: f64,
+    pub quantum_bits: f64,
+}
+
+/// Security estimate of a lattice basis
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityEstimate {
+    pub rank: usize,
+    pub dimension: usize,
+    pub log2_covolume: f64,
+    /// Base-2 logarithm of the Gaussian heuristic shortest vector length
+    pub log2_gaussian_heuristic: f64,
+    /// Root Hermite factor of the basis as given
+    pub root_hermite_factor: f64,
+    /// Measured root Hermite factor after LLL
+    pub lll_root_hermite_factor: f64,
+    /// Base-2 logarithm of the shortest LLL-reduced basis vector
+    pub lll_log2_shortest: f64,
+    /// Lovász swaps LLL needed
+    pub lll_swaps: usize,
+    pub bkz: Vec<BkzPrediction>,
+    /// `None` when the rank is below `MIN_ESTIMATE_RANK`
+    pub core_svp: Option<CoreSvpCost>,
+}
+
+/// Estimate the lattice spanned by the points of `lattice`
+pub fn estimate(lattice: &Lattice, block_sizes: &[usize]) -> Result<SecurityEstimate> {
+    estimate_basis(&LatticeBasis::from_lattice(lattice)?, block_sizes)
+}
+
+/// Estimate `basis`, predicting BKZ for each of `block_sizes` up to the rank
+pub fn estimate_basis(basis: &LatticeBasis, block_sizes: &[usize]) -> Result<SecurityEstimate> {
+    let rank = basis.rank();
+    let log2_covolume = basis.log2_covolume();
+    let log2_gaussian_heuristic = log2_gaussian_heuristic(rank, log2_covolume);
+    let predict = |block_size: usize| {
+        let root_hermite_factor = bkz_root_hermite_factor(block_size);
+        BkzPrediction {
+            block_size,
+            root_hermite_factor,
+            log2_shortest: rank as f64 * root_hermite_factor.log2() + log2_covolume / rank as f64,
+        }
+    };
+
+    let lll = basis.lll(DEFAULT_DELTA)?;
+    // A rank-β lattice is solved exactly by BKZ-β, whatever the prediction says
+    let svp_block_size = (2..=rank)
+        .find(|&block_size| predict(block_size).log2_shortest <= log2_gaussian_heuristic)
+        .unwrap_or(rank)
+        .max(2);
+    let core_svp = (rank >= MIN_ESTIMATE_RANK).then_some(CoreSvpCost {
+        block_size: svp_block_size,
+        classical_bits: CLASSICAL_SIEVE_EXPONENT * svp_block_size as f64,
+        quantum_bits: QUANTUM_SIEVE_EXPONENT * svp_block_size as f64,
+    });
+
+    Ok(SecurityEstimate {
+        rank,
+        dimension: basis.dimension(),
+        log2_covolume,
+        log2_gaussian_heuristic,
+        root_hermite_factor: basis.root_hermite_factor(),
+        lll_root_hermite_factor: lll.basis.root_hermite_factor(),
+        lll_log2_shortest: lll.basis.log2_norms().into_iter().fold(f64::INFINITY, f64::min),
+        lll_swaps: lll.swaps,
+        bkz: block_sizes.iter().copied().filter(|&block_size| block_size <= rank).map(predict).collect(),
+        core_svp,
+    })
+}
+
+impl fmt::Display for SecurityEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Lattice of rank {} in dimension {}", self.rank, self.dimension)?;
+        writeln!(f, "  {:<34} 2^{:.2}", "Covolume", self.log2_covolume)?;
+        writeln!(f, "  {:<34} 2^{:.2}", "Gaussian heuristic (shortest)", self.log2_gaussian_heuristic)?;
+        writeln!(f, "  {:<34} {:.6}", "Root Hermite factor (input)", self.root_hermite_factor)?;
+        writeln!(f, "  {:<34} {:.6} ({} swaps)", "Root Hermite factor (LLL)", self.lll_root_hermite_factor, self.lll_swaps)?;
+        writeln!(f, "  {:<34} 2^{:.2}", "Shortest LLL vector", self.lll_log2_shortest)?;
+        for prediction in &self.bkz {
+            writeln!(
+                f,
+                "  {:<34} {:.6}, first vector 2^{:.2}",
+                format!("Root Hermite factor (BKZ-{})", prediction.block_size),
+                prediction.root_hermite_factor,
+                prediction.log2_shortest
+            )?;
+        }
+        match &self.core_svp {
+            Some(cost) => {
+                writeln!(f, "  {:<34} {}", "Block size to reach the heuristic", cost.block_size)?;
+                writeln!(f, "  {:<34} {:.1} classical, {:.1} quantum", "Core-SVP security (bits)", cost.classical_bits, cost.quantum_bits)
+            }
+            None => writeln!(
+                f,
+                "  {:<34} not estimated: rank {} is below {}, so the lattice is solved outright",
+                "Core-SVP security (bits)", self.rank, MIN_ESTIMATE_RANK
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyper_prime::small_prime_sieve;
+    use crate::lattice::KeySchedule;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_root_hermite_factor_and_gaussian_heuristic() {
+        assert_eq!(bkz_root_hermite_factor(2), LLL_ROOT_HERMITE_FACTOR);
+        assert!((bkz_root_hermite_factor(12) - (1.01616 + (1.01485 - 1.01616) * 0.4)).abs() < 1e-12);
+        assert!((bkz_root_hermite_factor(100) - 1.00926).abs() < 1e-5);
+        let factors: Vec<f64> = (2..300).map(bkz_root_hermite_factor).collect();
+        assert!(factors.windows(2).all(|pair| pair[1] <= pair[0]));
+
+        // Stirling: sqrt(n / 2πe) (πn)^(1/2n)
+        let n = 400.0;
+        let approximation = (n / (2.0 * PI * E)).sqrt().log2() + (PI * n).log2() / (2.0 * n);
+        assert!((log2_gaussian_heuristic(400, 0.0) - approximation).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_estimate_small_lattice() {
+        let rows: Vec<Vec<BigInt>> = [[1, 0, 0, 0, 366], [0, 1, 0, 0, 385], [0, 0, 1, 0, 392], [0, 0, 0, 1, 401]]
+            .iter()
+            .map(|row| row.iter().map(|&x| BigInt::from(x)).collect())
+            .collect();
+        let estimate = estimate_basis(&LatticeBasis::new(rows).unwrap(), &DEFAULT_BLOCK_SIZES).unwrap();
+        assert_eq!(estimate.rank, 4);
+        assert!(estimate.bkz.is_empty());
+        assert!(estimate.lll_root_hermite_factor < estimate.root_hermite_factor);
+        assert!(estimate.lll_log2_shortest < estimate.log2_covolume);
+        assert!(estimate.core_svp.is_none());
+        assert!(estimate.to_string().contains("rank 4 is below 10"));
+
+        let rows: Vec<Vec<BigInt>> = (0..12).map(|i| (0..12).map(|j| BigInt::from(if i == j { 1000 + i } else { (i * 7 + j * 3) % 11 })).collect()).collect();
+        let estimate = estimate_basis(&LatticeBasis::new(rows).unwrap(), &DEFAULT_BLOCK_SIZES).unwrap();
+        let cost = estimate.core_svp.clone().unwrap();
+        assert!(cost.block_size <= 12);
+        assert_eq!(cost.classical_bits, CLASSICAL_SIEVE_EXPONENT * cost.block_size as f64);
+        assert!(estimate.to_string().contains("Core-SVP security (bits)"));
+    }
+
+    #[test]
+    fn test_cli_lattice_is_too_small_to_estimate() {
+        // The shape the CLI derives from a chaos key, 3 rows in dimension 256, with smaller primes
+        let lattice = Lattice::from_chaos_key_with_schedule(KeySchedule::LATEST, 42, b"hmac key", 256, 3, 64, &small_prime_sieve(1_000), 10).unwrap();
+        let estimate = estimate(&lattice, &DEFAULT_BLOCK_SIZES).unwrap();
+        assert_eq!((estimate.rank, estimate.dimension), (3, 256));
+        assert!(estimate.bkz.is_empty());
+        assert!(estimate.core_svp.is_none());
+        assert!(estimate.to_string().contains("not estimated: rank 3 is below 10"));
+    }
+}
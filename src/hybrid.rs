@@ -0,0 +1,123 @@
+//! Hybrid public-key encryption: the LWE KEM with the chaos cipher as DEM.
+//!
+//! The sender encapsulates a fresh shared secret to the recipient's LWE
+//! public key. HKDF-SHA3-256 expands it into the chaos seed, the S-Box key
+//! (which takes the place of the HMAC key when the lattice and its S-Box are
+//! built) and the container MAC key. The encapsulation travels in the
+//! container header under `KdfId::LweKem`, and the recipient decapsulates it
+//! with the key pair of their chaos key to rebuild the same keys.
+//! `seal_for_recipient` and `open_with_identity` run the whole exchange
+//! around a caller-supplied lattice constructor.
+
+use hkdf::Hkdf;
+use rand::{CryptoRng, RngCore};
+use sha3::Sha3_256;
+
+use crate::container::{CipherSuite, Container, KdfId};
+use crate::error::{Error, Result};
+use crate::lattice::{KeySchedule, Lattice};
+use crate::lwe::{LweCiphertext, LweKeyPair, LwePublicKey};
+use crate::mac::MacAlgorithm;
+use crate::nonce::{NonceGenerator, NONCE_LENGTH};
+
+/// HKDF salt, fixing the protocol and its version
+pub const HKDF_SALT: &[u8] = b"montgomery-chaos-lattices hybrid v1";
+
+/// Length of the S-Box and MAC keys
+pub const KEY_LENGTH: usize = 32;
+
+/// Cipher keys derived from a KEM shared secret
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HybridKeys {
+    /// Seed of the chaotic sequence
+    pub seed: u64,
+    /// Lattice key material, used in place of a chaos key's HMAC key
+    pub sbox_key: [u8; KEY_LENGTH],
+    /// Container MAC key
+    pub mac_key: [u8; KEY_LENGTH],
+}
+
+impl HybridKeys {
+    /// Expand a shared secret, with one HKDF `info` label per key
+    pub fn derive(shared_secret: &[u8]) -> Result<Self> {
+        let hkdf = Hkdf::<Sha3_256>::new(Some(HKDF_SALT), shared_secret);
+        let expand = |info: &[u8], okm: &mut [u8]| hkdf.expand(info, okm).map_err(|e| Error::Format(format!("HKDF expansion failed: {}", e)));
+
+        let mut seed = [0u8; 8];
+        let mut sbox_key = [0u8; KEY_LENGTH];
+        let mut mac_key = [0u8; KEY_LENGTH];
+        expand(b"chaos seed", &mut seed)?;
+        expand(b"sbox key", &mut sbox_key)?;
+        expand(b"mac key", &mut mac_key)?;
+        Ok(Self { seed: u64::from_be_bytes(seed), sbox_key, mac_key })
+    }
+}
+
+/// Encapsulate fresh keys to `recipient`, returning them and the encapsulation bytes
+pub fn encapsulate<R: RngCore + CryptoRng>(recipient: &LwePublicKey, rng: &mut R) -> Result<(HybridKeys, Vec<u8>)> {
+    let (shared_secret, encapsulation) = recipient.encapsulate(rng);
+    Ok((HybridKeys::derive(&shared_secret)?, encapsulation.to_bytes()))
+}
+
+/// Recover the keys of an encapsulation. A tampered encapsulation yields
+/// unrelated keys, which the container MAC then rejects.
+pub fn decapsulate(identity: &LweKeyPair, encapsulation: &[u8]) -> Result<HybridKeys> {
+    HybridKeys::derive(&identity.decapsulate(&LweCiphertext::from_bytes(encapsulation)?))
+}
+
+/// Encapsulate fresh keys to `recipient` and encrypt `plaintext` under them
+/// in a `KdfId::LweKem` container.
+///
+/// `build_lattice` derives the lattice from a seed and S-Box key under a key
+/// schedule; `Lattice::encrypt_with_nonce` checks the per-message S-Box
+/// against the thresholds it was built with.
+pub fn seal_for_recipient<R, F>(recipient: &LwePublicKey, plaintext: &[u8], rng: &mut R, nonces: &mut NonceGenerator, build_lattice: F) -> Result<Container>
+where
+    R: RngCore + CryptoRng,
+    F: Fn(u64, &[u8], KeySchedule) -> Result<Lattice>,
+{
+    let (keys, encapsulation) = encapsulate(recipient, rng)?;
+    let lattice = build_lattice(keys.seed, &keys.sbox_key, CipherSuite::LATEST.key_schedule())?;
+    let (nonce, ciphertext) = lattice.encrypt_with_nonce(plaintext, keys.seed, nonces)?;
+    Container::seal_with_encapsulation(KdfId::LweKem, CipherSuite::LATEST, MacAlgorithm::HmacSha3_256, &keys.mac_key, nonce, encapsulation, ciphertext)
+}
+
+/// Decapsulate, verify and decrypt a `KdfId::LweKem` container sent to `identity`
+pub fn open_with_identity<F>(identity: &LweKeyPair, container: &Container, build_lattice: F) -> Result<Vec<u8>>
+where
+    F: Fn(u64, &[u8], KeySchedule) -> Result<Lattice>,
+{
+    if container.kdf != KdfId::LweKem {
+        return Err(Error::Format(format!("KDF {} does not carry an encapsulation.", container.kdf.id())));
+    }
+    if container.nonce.len() != NONCE_LENGTH {
+        return Err(Error::Length { expected: NONCE_LENGTH, found: container.nonce.len() });
+    }
+
+    let keys = decapsulate(identity, &container.encapsulation)?;
+    container.verify(&keys.mac_key)?;
+    let lattice = build_lattice(keys.seed, &keys.sbox_key, container.cipher.key_schedule())?;
+    Ok(lattice.decrypt_with_nonce(&container.ciphertext, keys.seed, &container.nonce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_encapsulate_decapsulate() {
+        let identity = LweKeyPair::from_chaos_seed(7, &[0x5a; 32]);
+        let mut rng = ChaCha20Rng::seed_from_u64(25);
+        let (keys, encapsulation) = encapsulate(&identity.public, &mut rng).unwrap();
+        assert_eq!(decapsulate(&identity, &encapsulation).unwrap(), keys);
+        assert_ne!(keys.sbox_key, keys.mac_key);
+        assert_ne!(encapsulate(&identity.public, &mut rng).unwrap().0, keys);
+
+        let stranger = LweKeyPair::from_chaos_seed(8, &[0x5a; 32]);
+        assert_ne!(decapsulate(&stranger, &encapsulation).unwrap(), keys);
+        assert!(decapsulate(&identity, &encapsulation[1..]).is_err());
+        assert_eq!(HybridKeys::derive(&[1; 32]).unwrap(), HybridKeys::derive(&[1; 32]).unwrap());
+    }
+}
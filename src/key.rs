@@ -0,0 +1,339 @@
+//! Chaos key generation and encoding.
+//!
+//! Keys are stored as the hex encoding of
+//! `MCLK || version || record* || tag_record` inside `CHAOS KEY` markers,
+//! where each record is `type || length (u16 BE) || value`. The last record
+//! is an HMAC-SHA3-256 tag, keyed with the HMAC key, over every byte before
+//! it. Because the key size is a record, keys parse without knowing `bits`.
+//!
+//! Keys from older releases (`nonce_hex || hmac_key_hex || sequence_hex || tag_hex`)
+//! are still read; their size is inferred from the total length.
+//!
+//! A key can also be stored under `ENCRYPTED CHAOS KEY` markers, where the
+//! record bytes are wrapped under a passphrase by `passphrase::wrap`.
+
+use num_bigint::BigUint;
+use rand::rngs::OsRng;
+use sha3::{Digest, Sha3_512};
+use std::fs::{read_to_string, write};
+
+use crate::chaos::chaotic_sequence;
+use crate::error::{Error, Result};
+use crate::hyper_prime::{generate_hyper_prime, small_prime_sieve};
+use crate::mac::{constant_time_eq, legacy_tag, Mac, MacAlgorithm};
+use crate::passphrase::{self, PassphraseParams};
+use crate::pem::{unwrap_pem_format, wrap_in_pem_format};
+
+/// Magic bytes at the start of an encoded key
+pub const KEY_MAGIC: &[u8; 4] = b"MCLK";
+
+/// Key format version written by this release
+pub const KEY_VERSION: u8 = 2;
+
+/// Armor label of key files
+pub const KEY_LABEL: &str = "CHAOS KEY";
+
+/// Armor label of passphrase-protected key files
+pub const ENCRYPTED_KEY_LABEL: &str = "ENCRYPTED CHAOS KEY";
+
+const RECORD_SEED: u8 = 0x01;
+const RECORD_HMAC_KEY: u8 = 0x02;
+const RECORD_BITS: u8 = 0x03;
+const RECORD_TAG: u8 = 0xff;
+
+const LEGACY_SEED_HEX_LENGTH: usize = 16; // 64-bit nonce = 16 hex characters
+const LEGACY_DATA_HEX_LENGTH: usize = 512; // 256-byte chaos sequence = 512 hex characters
+const LEGACY_TAG_HEX_LENGTH: usize = 64; // HMAC-SHA3-256 = 256 bits = 64 hex characters
+
+/// A decoded chaos key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChaosKey {
+    /// Seed of the chaotic sequence
+    pub seed: u64,
+    /// HMAC key prime
+    pub hmac_key: BigUint,
+    /// Size of the HMAC key prime in bits
+    pub bits: usize,
+}
+
+impl ChaosKey {
+    /// Generate a fresh key whose HMAC key prime has `bits` bits
+    pub fn generate(bits: usize, small_prime_limit: usize) -> Result<Self> {
+        // Generate the nonce (seed)
+        let seed = generate_nonce(bits, small_prime_limit)?;
+
+        // Generate the HMAC key prime
+        let hmac_key = generate_hyper_prime(&mut OsRng, bits, &small_prime_sieve(small_prime_limit), 40)?;
+
+        Ok(Self { seed, hmac_key, bits })
+    }
+
+    /// The HMAC key as big-endian bytes
+    pub fn hmac_key_bytes(&self) -> Vec<u8> {
+        self.hmac_key.to_bytes_be()
+    }
+}
+
+/// Generate the chaos key in PEM-like format
+pub fn generate_chaos_key(bits: usize, small_prime_limit: usize) -> Result<String> {
+    Ok(encode_chaos_key(&ChaosKey::generate(bits, small_prime_limit)?))
+}
+
+/// Generate a 64-bit nonce (seed)
+pub fn generate_nonce(bits: usize, small_prime_limit: usize) -> Result<u64> {
+    let small_primes = small_prime_sieve(small_prime_limit);
+    let mut rng = OsRng;
+    let rounds = 40;
+    let chaos_seed_big = generate_hyper_prime(&mut rng, bits, &small_primes, rounds)?;
+    let mut hasher = Sha3_512::new();
+    hasher.update(chaos_seed_big.to_bytes_be());
+    let chaos_seed_bytes = hasher.finalize();
+    Ok(u64::from_be_bytes(chaos_seed_bytes[0..8].try_into().unwrap()))
+}
+
+/// Encode a chaos key in the current PEM-like format
+pub fn encode_chaos_key(key: &ChaosKey) -> String {
+    wrap_in_pem_format(KEY_LABEL, &hex::encode(key_records(key)))
+}
+
+/// Encode a chaos key protected by a passphrase
+pub fn encrypt_chaos_key(key: &ChaosKey, passphrase: &[u8], params: &PassphraseParams) -> Result<String> {
+    let wrapped = passphrase::wrap(&key_records(key), passphrase, params)?;
+    Ok(wrap_in_pem_format(ENCRYPTED_KEY_LABEL, &hex::encode(wrapped)))
+}
+
+/// Serialize a chaos key to tagged records
+fn key_records(key: &ChaosKey) -> Vec<u8> {
+    let hmac_key = key.hmac_key_bytes();
+
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(KEY_MAGIC);
+    encoded.push(KEY_VERSION);
+    push_record(&mut encoded, RECORD_SEED, &key.seed.to_be_bytes());
+    push_record(&mut encoded, RECORD_BITS, &(key.bits as u32).to_be_bytes());
+    push_record(&mut encoded, RECORD_HMAC_KEY, &hmac_key);
+
+    let tag = Mac::new(MacAlgorithm::HmacSha3_256, &hmac_key).compute(&encoded);
+    push_record(&mut encoded, RECORD_TAG, &tag);
+    encoded
+}
+
+/// Append one `type || length || value` record
+pub(crate) fn push_record(encoded: &mut Vec<u8>, record_type: u8, value: &[u8]) {
+    encoded.push(record_type);
+    encoded.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    encoded.extend_from_slice(value);
+}
+
+/// Save a chaos key to a file
+pub fn save_chaos_key(filename: &str, key: &str) -> Result<()> {
+    Ok(write(filename, key)?)
+}
+
+/// Decode a chaos key file in any supported format.
+///
+/// `passphrase` is only used, and then required, for passphrase-protected keys.
+pub fn decode_chaos_key(filename: &str, passphrase: Option<&[u8]>) -> Result<ChaosKey> {
+    parse_chaos_key(&read_to_string(filename)?, passphrase)
+}
+
+/// Parse a PEM-like chaos key in any supported format
+pub fn parse_chaos_key(content: &str, passphrase: Option<&[u8]>) -> Result<ChaosKey> {
+    if let Ok(encoded_data) = unwrap_pem_format(ENCRYPTED_KEY_LABEL, content) {
+        let passphrase = passphrase.ok_or(Error::PassphraseRequired)?;
+        return parse_records(&passphrase::unwrap(&hex::decode(encoded_data)?, passphrase)?);
+    }
+
+    // Extract the encoded data between the markers
+    let encoded_data = unwrap_pem_format(KEY_LABEL, content)?;
+    if !encoded_data.is_ascii() {
+        return Err(Error::Format("Chaos key data is not hexadecimal.".to_string()));
+    }
+
+    if is_legacy_chaos_key(&encoded_data) {
+        parse_legacy_chaos_key(&encoded_data)
+    } else {
+        parse_records(&hex::decode(&encoded_data)?)
+    }
+}
+
+/// Whether the armored payload is in the pre-record layout
+fn is_legacy_chaos_key(encoded_data: &str) -> bool {
+    !encoded_data
+        .to_ascii_lowercase()
+        .starts_with(&hex::encode(KEY_MAGIC))
+}
+
+/// Parse the record layout
+fn parse_records(encoded: &[u8]) -> Result<ChaosKey> {
+    if encoded.len() < KEY_MAGIC.len() + 1 {
+        return Err(Error::Length {
+            expected: KEY_MAGIC.len() + 1,
+            found: encoded.len(),
+        });
+    }
+    if &encoded[0..4] != KEY_MAGIC {
+        return Err(Error::Format("Missing chaos key magic bytes.".to_string()));
+    }
+    if encoded[4] != KEY_VERSION {
+        return Err(Error::Format(format!("Unsupported chaos key version {}.", encoded[4])));
+    }
+
+    let mut seed = None;
+    let mut bits = None;
+    let mut hmac_key = None;
+    let mut position = KEY_MAGIC.len() + 1;
+    loop {
+        let (record_type, value) = next_record(encoded, position)?.ok_or_else(|| Error::Format("Chaos key ends before its tag.".to_string()))?;
+        let length = value.len();
+
+        match record_type {
+            RECORD_SEED => {
+                let bytes: [u8; 8] = value
+                    .try_into()
+                    .map_err(|_| Error::Format("Invalid nonce byte length.".to_string()))?;
+                seed = Some(u64::from_be_bytes(bytes));
+            }
+            RECORD_BITS => {
+                let bytes: [u8; 4] = value
+                    .try_into()
+                    .map_err(|_| Error::Format("Invalid key size record.".to_string()))?;
+                bits = Some(u32::from_be_bytes(bytes) as usize);
+            }
+            RECORD_HMAC_KEY => hmac_key = Some(value.to_vec()),
+            RECORD_TAG => {
+                if position + 3 + length != encoded.len() {
+                    return Err(Error::Format("Data found after the chaos key tag.".to_string()));
+                }
+                let hmac_key = hmac_key.ok_or_else(|| Error::Format("Chaos key has no HMAC key.".to_string()))?;
+                Mac::new(MacAlgorithm::HmacSha3_256, &hmac_key).verify(&encoded[..position], value)?;
+
+                let seed = seed.ok_or_else(|| Error::Format("Chaos key has no seed.".to_string()))?;
+                let bits = bits.ok_or_else(|| Error::Format("Chaos key has no size.".to_string()))?;
+                if hmac_key.len() * 8 != bits {
+                    return Err(Error::Length {
+                        expected: bits / 8,
+                        found: hmac_key.len(),
+                    });
+                }
+                return Ok(ChaosKey {
+                    seed,
+                    hmac_key: BigUint::from_bytes_be(&hmac_key),
+                    bits,
+                });
+            }
+            // Unknown records are authenticated by the tag but otherwise ignored
+            _ => {}
+        }
+        position += 3 + length;
+    }
+}
+
+/// The `(type, value)` record starting at `position`, or `None` if fewer
+/// than three bytes are left
+pub(crate) fn next_record(encoded: &[u8], position: usize) -> Result<Option<(u8, &[u8])>> {
+    if encoded.len() < position + 3 {
+        return Ok(None);
+    }
+    let length = u16::from_be_bytes([encoded[position + 1], encoded[position + 2]]) as usize;
+    let value = encoded
+        .get(position + 3..position + 3 + length)
+        .ok_or_else(|| Error::Format("Key record is truncated.".to_string()))?;
+    Ok(Some((encoded[position], value)))
+}
+
+/// Parse the legacy hex layout, inferring the key size from its length
+fn parse_legacy_chaos_key(encoded_data: &str) -> Result<ChaosKey> {
+    let fixed_length = LEGACY_SEED_HEX_LENGTH + LEGACY_DATA_HEX_LENGTH + LEGACY_TAG_HEX_LENGTH;
+    let hmac_key_hex_length = encoded_data.len().saturating_sub(fixed_length);
+    let bits = hmac_key_hex_length * 4;
+    if bits < 64 || !bits.is_multiple_of(64) {
+        return Err(Error::Length {
+            expected: fixed_length + 16,
+            found: encoded_data.len(),
+        });
+    }
+
+    // Extract seed_hex, hmac_key_hex, data_hex, hmac_value
+    let (seed_hex, rest) = encoded_data.split_at(LEGACY_SEED_HEX_LENGTH);
+    let (hmac_key_hex, rest) = rest.split_at(hmac_key_hex_length);
+    let (data_hex, hmac_value) = rest.split_at(LEGACY_DATA_HEX_LENGTH);
+
+    // Convert hex to bytes
+    let seed_array: [u8; 8] = hex::decode(seed_hex)?
+        .try_into()
+        .map_err(|_| Error::Format("Invalid nonce byte length.".to_string()))?;
+    let seed = u64::from_be_bytes(seed_array);
+    let hmac_key = hex::decode(hmac_key_hex)?;
+    let _data_bytes = hex::decode(data_hex)?;
+    let tag = hex::decode(hmac_value)?;
+
+    // The tag covers the chaotic sequence regenerated from the seed
+    let data_string: String = chaotic_sequence(256, seed)
+        .iter()
+        .map(|&num| format!("{:02x}", num))
+        .collect();
+
+    // Verify HMAC, accepting the prefix-hash tag written by the oldest releases
+    let mac = Mac::new(MacAlgorithm::HmacSha3_256, &hmac_key);
+    if mac.verify(data_string.as_bytes(), &tag).is_err() && !constant_time_eq(&legacy_tag(&hmac_key, data_string.as_bytes()), &tag) {
+        return Err(Error::Authentication);
+    }
+
+    Ok(ChaosKey {
+        seed,
+        hmac_key: BigUint::from_bytes_be(&hmac_key),
+        bits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key() -> ChaosKey {
+        let hmac_key = BigUint::from_bytes_be(&[0xa5; 32]);
+        ChaosKey { seed: 0x0123_4567_89ab_cdef, hmac_key, bits: 256 }
+    }
+
+    #[test]
+    fn test_encode_parse_roundtrip() {
+        let key = test_key();
+        let encoded = encode_chaos_key(&key);
+        assert_eq!(parse_chaos_key(&encoded, None).unwrap(), key);
+
+        // Flip one hex digit of the seed record
+        let tampered = encoded.replacen("0123456789abcdef", "1123456789abcdef", 1);
+        assert!(matches!(parse_chaos_key(&tampered, None), Err(Error::Authentication)));
+    }
+
+    #[test]
+    fn test_passphrase_protected_key() {
+        let key = test_key();
+        let params = PassphraseParams {
+            memory_cost: 64,
+            time_cost: 1,
+            parallelism: 1,
+        };
+        let encoded = encrypt_chaos_key(&key, b"passphrase", &params).unwrap();
+
+        assert!(!encoded.contains(&hex::encode(key.hmac_key_bytes())));
+        assert_eq!(parse_chaos_key(&encoded, Some(b"passphrase")).unwrap(), key);
+        assert!(matches!(parse_chaos_key(&encoded, None), Err(Error::PassphraseRequired)));
+        assert!(matches!(parse_chaos_key(&encoded, Some(b"guess")), Err(Error::Authentication)));
+    }
+
+    #[test]
+    fn test_parses_legacy_key_without_bits() {
+        let key = test_key();
+        let hmac_key = key.hmac_key_bytes();
+        let data_string: String = chaotic_sequence(256, key.seed)
+            .iter()
+            .map(|&num| format!("{:02x}", num))
+            .collect();
+        let tag = legacy_tag(&hmac_key, data_string.as_bytes());
+        let encoded = format!("{:016x}{}{}{}", key.seed, hex::encode(&hmac_key), data_string, hex::encode(tag));
+
+        assert_eq!(parse_chaos_key(&wrap_in_pem_format(KEY_LABEL, &encoded), None).unwrap(), key);
+    }
+}
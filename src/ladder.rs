@@ -0,0 +1,143 @@
+//! Constant-time Montgomery ladder over `Z_q`.
+//!
+//! Coordinates live in the prime field `Z_q` with `q = 2^255 - 19`, held as
+//! fixed-width four-limb residues in Montgomery form (`crypto_bigint`), so
+//! every addition and doubling costs the same whatever the value. The ladder
+//! walks all `SCALAR_BITS` bits of a fixed-width scalar and uses a
+//! conditional swap instead of a branch, so neither the control flow nor
+//! the memory access pattern depends on the scalar. Without chaos offsets it
+//! computes the scalar multiple `k * P` in the additive group `Z_q^n`.
+//!
+//! Conversion from and to `BigUint` at the boundary is not constant time;
+//! keep secrets in `Fq` form where that matters.
+
+use crypto_bigint::modular::constant_mod::Residue;
+use crypto_bigint::subtle::{Choice, ConditionallySelectable};
+use crypto_bigint::{impl_modulus, Encoding};
+use num_bigint::BigUint;
+
+pub use crypto_bigint::U256;
+
+use crate::error::{Error, Result};
+
+impl_modulus!(FieldModulus, U256, "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed");
+
+/// An element of `Z_q`
+pub type Fq = Residue<FieldModulus, { U256::LIMBS }>;
+
+/// Number of scalar bits the ladder always processes
+pub const SCALAR_BITS: usize = 256;
+
+/// The field modulus `q = 2^255 - 19`
+pub fn modulus() -> BigUint {
+    (BigUint::from(1u32) << 255u32) - BigUint::from(19u32)
+}
+
+/// Reduce `value` into `Z_q`
+pub fn fq_from_biguint(value: &BigUint) -> Fq {
+    let reduced = (value % modulus()).to_bytes_be();
+    let mut bytes = [0u8; 32];
+    bytes[32 - reduced.len()..].copy_from_slice(&reduced);
+    Fq::new(&U256::from_be_bytes(bytes))
+}
+
+/// Canonical representative of `value` in `[0, q)`
+pub fn fq_to_biguint(value: &Fq) -> BigUint {
+    BigUint::from_bytes_be(&value.retrieve().to_be_bytes())
+}
+
+/// Convert a scalar of at most `SCALAR_BITS` bits to fixed width
+pub fn scalar_from_biguint(scalar: &BigUint) -> Result<U256> {
+    if scalar.bits() > SCALAR_BITS as u64 {
+        return Err(Error::Lattice(format!("The ladder scalar exceeds {} bits.", SCALAR_BITS)));
+    }
+    let bytes = scalar.to_bytes_be();
+    let mut padded = [0u8; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(U256::from_be_bytes(padded))
+}
+
+/// Swap `a` and `b` element-wise if `choice` is set, without branching
+fn conditional_swap(a: &mut [Fq], b: &mut [Fq], choice: Choice) {
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        Fq::conditional_swap(x, y, choice);
+    }
+}
+
+/// Scalar multiple `scalar * point` in `Z_q^n`
+pub fn ladder(scalar: &U256, point: &[Fq]) -> Vec<Fq> {
+    ladder_with_chaos(scalar, point, &[])
+}
+
+/// Montgomery ladder with a chaos offset added to every ladder addition.
+///
+/// Step `i` (from the most significant bit) adds `chaos_seq[i]`, or nothing
+/// past the end of the sequence; the step index is public, so the lookup
+/// does not depend on secrets.
+pub fn ladder_with_chaos(scalar: &U256, point: &[Fq], chaos_seq: &[usize]) -> Vec<Fq> {
+    let mut r0 = vec![Fq::ZERO; point.len()];
+    let mut r1 = point.to_vec();
+    let mut swapped = Choice::from(0);
+
+    for step in 0..SCALAR_BITS {
+        let bit = Choice::from(scalar.bit(SCALAR_BITS - 1 - step));
+        conditional_swap(&mut r0, &mut r1, swapped ^ bit);
+        swapped = bit;
+
+        let offset = Fq::new(&U256::from_u64(chaos_seq.get(step).map_or(0, |&value| value as u64)));
+        for (x0, x1) in r0.iter_mut().zip(r1.iter_mut()) {
+            *x1 = *x0 + *x1 + offset;
+            *x0 = *x0 + *x0;
+        }
+    }
+    conditional_swap(&mut r0, &mut r1, swapped);
+    r0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Branching reference ladder over `BigUint` mod `q`
+    fn reference(scalar: &BigUint, point: &[BigUint], chaos_seq: &[usize]) -> Vec<BigUint> {
+        let q = modulus();
+        let mut r0 = vec![BigUint::from(0u32); point.len()];
+        let mut r1 = point.to_vec();
+        for step in 0..SCALAR_BITS {
+            let offset = BigUint::from(chaos_seq.get(step).copied().unwrap_or(0));
+            let bit = scalar.bit((SCALAR_BITS - 1 - step) as u64);
+            let (a, b) = if bit { (&mut r1, &mut r0) } else { (&mut r0, &mut r1) };
+            for (x0, x1) in a.iter_mut().zip(b.iter_mut()) {
+                *x1 = (&*x0 + &*x1 + &offset) % &q;
+                *x0 = (&*x0 << 1u32) % &q;
+            }
+        }
+        r0
+    }
+
+    #[test]
+    fn test_ladder_is_scalar_multiplication() {
+        let q = modulus();
+        let point = [BigUint::from(3u32), &q - 1u32, BigUint::from(1u32) << 300u32];
+        let fq_point: Vec<Fq> = point.iter().map(fq_from_biguint).collect();
+
+        for scalar in [BigUint::from(0u32), BigUint::from(1u32), BigUint::from(0xdead_beefu64), (BigUint::from(1u32) << 256u32) - 1u32] {
+            let expected: Vec<BigUint> = point.iter().map(|x| x * &scalar % &q).collect();
+            let result = ladder(&scalar_from_biguint(&scalar).unwrap(), &fq_point);
+            assert_eq!(result.iter().map(fq_to_biguint).collect::<Vec<_>>(), expected);
+        }
+
+        assert!(scalar_from_biguint(&(BigUint::from(1u32) << 256u32)).is_err());
+    }
+
+    #[test]
+    fn test_ladder_with_chaos_matches_reference() {
+        let point: Vec<BigUint> = (1..=4u32).map(|x| BigUint::from(x) << (60 * x)).collect();
+        let fq_point: Vec<Fq> = point.iter().map(fq_from_biguint).collect();
+        let scalar = BigUint::parse_bytes(b"c0ffee0123456789abcdef0123456789abcdef0123456789abcdef01234567", 16).unwrap();
+        let chaos_seq = [5, 1, 7, 3, 0, 2, 6, 4];
+
+        let result = ladder_with_chaos(&scalar_from_biguint(&scalar).unwrap(), &fq_point, &chaos_seq);
+        assert_eq!(result.iter().map(fq_to_biguint).collect::<Vec<_>>(), reference(&scalar, &point, &chaos_seq));
+    }
+}
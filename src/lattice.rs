@@ -1,48 +1,195 @@
 use chacha20::cipher::{NewCipher, StreamCipher};
 use chacha20::ChaCha20;
-use num_bigint::{BigUint, RandBigInt};
-use num_traits::{One, Zero};
+use num_bigint::BigUint;
+use num_traits::{CheckedSub, One, Zero};
 use rand::rngs::OsRng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use sha3::{Digest, Sha3_256};
 
+use crate::chaos::SequenceAlgorithm;
+use crate::error::{Error, Result};
+use crate::hyper_prime::{generate_hyper_prime, is_probably_prime, small_prime_sieve};
+use crate::ladder::{self, Fq, U256};
+use crate::montgomery::{MontgomeryContext, MontgomeryForm};
+use crate::nonce::{Nonce, NonceGenerator};
+use crate::sbox_analysis::{analyze_sbox, SboxThresholds};
+
+/// Nonces `encrypt_with_nonce` tries before giving up on a weak S-Box
+pub const MAX_SBOX_ATTEMPTS: usize = 16;
+
 /// Structure for a lattice point
 #[derive(Debug, Clone)]
 pub struct LatticePoint {
     pub coordinates: Vec<BigUint>, // Coordinates in the lattice
 }
 
+/// How the key schedule binds the lattice rows with the chaos sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// `bind_with_chaos` over unbounded integers: branches on the scalar bits;
+    /// kept for existing ciphertexts
+    Legacy,
+    /// `bind_with_chaos_constant_time` over `Z_q` with `q = 2^255 - 19`
+    ConstantTime,
+}
+
+/// The chaotic sequence and row binding a lattice key schedule runs with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySchedule {
+    pub sequence: SequenceAlgorithm,
+    pub binding: Binding,
+}
+
+impl KeySchedule {
+    /// Schedule used for newly written data
+    pub const LATEST: Self = Self { sequence: SequenceAlgorithm::LATEST, binding: Binding::ConstantTime };
+
+    /// The variable-time schedule on `sequence`, as used by older releases
+    pub const fn legacy(sequence: SequenceAlgorithm) -> Self {
+        Self { sequence, binding: Binding::Legacy }
+    }
+}
+
 /// Structure for the lattice
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Lattice {
     points: Vec<LatticePoint>,    // Lattice points
     dimensions: usize,            // Number of dimensions
     prime_anchors: Vec<BigUint>,  // Prime anchors derived from rows
     sbox: [u8; 256],              // Substitution box
     inverse_sbox: [u8; 256],      // Inverse substitution box
+    schedule: KeySchedule,        // Chaotic sequence behind the keystream and S-Box, and the row binding
+    modulus: Option<BigUint>,     // Prime q for binding over Z_q; None only for the legacy key schedule
+    sbox_thresholds: SboxThresholds, // Limits every per-message S-Box must meet
 }
 
 impl Lattice {
     /// Create a new lattice with random points
-    pub fn new(dimensions: usize, size: usize, prime_bits: usize, small_primes: &[u64], rounds: usize) -> Self {
+    pub fn new(dimensions: usize, size: usize, prime_bits: usize, small_primes: &[u64], rounds: usize) -> Result<Self> {
+        Self::new_with_rng(&mut OsRng, dimensions, size, prime_bits, small_primes, rounds)
+    }
+
+    /// Create a lattice deterministically from key material.
+    ///
+    /// The points are drawn from a ChaCha20 CSPRNG seeded with SHA3-256 of
+    /// `key_material`, so the same material always yields the same lattice.
+    pub fn from_key_material(key_material: &[u8], dimensions: usize, size: usize, prime_bits: usize, small_primes: &[u64], rounds: usize) -> Result<Self> {
+        let mut rng = ChaCha20Rng::from_seed(Sha3_256::digest(key_material).into());
+        Self::new_with_rng(&mut rng, dimensions, size, prime_bits, small_primes, rounds)
+    }
+
+    /// Derive the full lattice key schedule from a chaos key.
+    ///
+    /// The lattice is built from the seed and HMAC key, bound with the chaos
+    /// sequence, and its prime anchors are mixed into every per-message S-Box
+    /// of `encrypt_with_nonce`, so encryptor and decryptor rebuild the same
+    /// S-Boxes and the lattice contributes to them. The S-Box of the nonce-free
+    /// `encrypt` stays anchor-free, as legacy ciphertexts require. This uses the
+    /// legacy schedule on `SequenceAlgorithm::Trigonometric`; see
+    /// `from_chaos_key_with_schedule`.
+    pub fn from_chaos_key(seed: u64, hmac_key: &[u8], dimensions: usize, size: usize, prime_bits: usize, small_primes: &[u64], rounds: usize) -> Result<Self> {
+        Self::from_chaos_key_with_schedule(KeySchedule::legacy(SequenceAlgorithm::Trigonometric), seed, hmac_key, dimensions, size, prime_bits, small_primes, rounds)
+    }
+
+    /// Derive the lattice key schedule from a chaos key with the given
+    /// sequence algorithm and binding.
+    ///
+    /// `Binding::ConstantTime` binds with the constant-time ladder; the
+    /// `BigUint` conversions around it are not constant time, but only
+    /// touch the lattice points, never the scalar.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_chaos_key_with_schedule(
+        schedule: KeySchedule,
+        seed: u64,
+        hmac_key: &[u8],
+        dimensions: usize,
+        size: usize,
+        prime_bits: usize,
+        small_primes: &[u64],
+        rounds: usize,
+    ) -> Result<Self> {
+        let mut key_material = b"lattice".to_vec();
+        key_material.extend_from_slice(&seed.to_be_bytes());
+        key_material.extend_from_slice(hmac_key);
+
+        let mut lattice = Self::from_key_material(&key_material, dimensions, size, prime_bits, small_primes, rounds)?;
+        lattice.schedule = schedule;
+        let chaos_seq = schedule.sequence.sequence(dimensions, seed);
+        match schedule.binding {
+            Binding::Legacy => {
+                lattice.modulus = None;
+                lattice.bind_with_chaos(&BigUint::from(2u64), &chaos_seq)?
+            }
+            Binding::ConstantTime => lattice.bind_with_chaos_constant_time(&U256::from_u64(2), &chaos_seq),
+        }
+        lattice.generate_prime_anchors();
+        lattice.generate_sbox(seed)?;
+        Ok(lattice)
+    }
+
+    /// Create a new lattice with points drawn from `rng`
+    pub fn new_with_rng<R: Rng + ?Sized>(rng: &mut R, dimensions: usize, size: usize, prime_bits: usize, small_primes: &[u64], rounds: usize) -> Result<Self> {
         let mut points = Vec::new();
-        let mut rng = OsRng;
 
         for _ in 0..size {
             let mut coordinates = Vec::new();
             for _ in 0..dimensions {
-                let prime = generate_hyper_prime(&mut rng, prime_bits, small_primes, rounds);
+                let prime = generate_hyper_prime(rng, prime_bits, small_primes, rounds)?;
                 coordinates.push(prime);
             }
             points.push(LatticePoint { coordinates });
         }
 
-        Self {
+        Ok(Self {
             points,
             dimensions,
             prime_anchors: Vec::new(),
-            sbox: [0u8; 256],
-            inverse_sbox: [0u8; 256],
-        }
+            sbox: IDENTITY_SBOX,
+            inverse_sbox: IDENTITY_SBOX,
+            schedule: KeySchedule::legacy(SequenceAlgorithm::Trigonometric),
+            modulus: Some(ladder::modulus()),
+            sbox_thresholds: SboxThresholds::default(),
+        })
+    }
+
+    /// Get coordinates of all lattice points
+    pub fn get_coordinates(&self) -> Vec<Vec<BigUint>> {
+        self.points
+            .iter()
+            .map(|point| point.coordinates.clone())
+            .collect()
+    }
+
+    /// Get the substitution box
+    pub fn sbox(&self) -> &[u8; 256] {
+        &self.sbox
+    }
+
+    /// Get the inverse substitution box
+    pub fn inverse_sbox(&self) -> &[u8; 256] {
+        &self.inverse_sbox
+    }
+
+    /// Get the key schedule the lattice was derived with
+    pub fn key_schedule(&self) -> KeySchedule {
+        self.schedule
+    }
+
+    /// Get the chaotic sequence algorithm
+    pub fn sequence_algorithm(&self) -> SequenceAlgorithm {
+        self.schedule.sequence
+    }
+
+    /// Select the chaotic map used by the S-Box and the cipher; call
+    /// `generate_sbox` afterwards to rebuild the S-Box of `encrypt` from it
+    pub fn set_sequence_algorithm(&mut self, sequence: SequenceAlgorithm) {
+        self.schedule.sequence = sequence;
+    }
+
+    /// Set the limits the S-Box of every `encrypt_with_nonce` message must meet
+    pub fn set_sbox_thresholds(&mut self, thresholds: SboxThresholds) {
+        self.sbox_thresholds = thresholds;
     }
 
     /// Print the lattice points
@@ -58,32 +205,122 @@ impl Lattice {
         }
     }
 
-    /// Perform Montgomery ladder binding on rows
-    pub fn bind_rows_with_ladder(&mut self, scalar: &BigUint) {
+    /// Get the binding modulus. New lattices bind over `Z_q` with
+    /// `q = 2^255 - 19`; only lattices of the legacy key schedule bind over
+    /// unbounded integers and have none.
+    pub fn modulus(&self) -> Option<&BigUint> {
+        self.modulus.as_ref()
+    }
+
+    /// Bind over `Z_q` for the odd prime `q`: ladder steps and
+    /// orthogonalization are reduced mod `q`, so coordinates stay below `q`
+    /// for any scalar length and orthogonalization cannot fail
+    pub fn set_modulus(&mut self, modulus: BigUint) -> Result<()> {
+        let small_primes = small_prime_sieve(1_000);
+        if modulus <= BigUint::from(2u32) || !is_probably_prime(&mut OsRng, &modulus, &small_primes, 40) {
+            return Err(Error::Lattice("The binding modulus must be an odd prime.".to_string()));
+        }
+        self.modulus = Some(modulus);
+        Ok(())
+    }
+
+    /// Bind over `Z_q` with `q` the largest prime anchor
+    pub fn set_modulus_from_anchors(&mut self) -> Result<()> {
+        let largest = self
+            .prime_anchors
+            .iter()
+            .max()
+            .cloned()
+            .ok_or_else(|| Error::Lattice("Generate prime anchors before taking the modulus from them.".to_string()))?;
+        self.set_modulus(largest)
+    }
+
+    /// Perform Montgomery ladder binding on rows.
+    ///
+    /// This legacy ladder branches on each scalar bit, so its timing depends
+    /// on the scalar; it is kept because existing keys derive from it. Use
+    /// `bind_rows_constant_time` for secret scalars. Coordinates are reduced
+    /// mod `modulus()`; see `set_modulus`.
+    pub fn bind_rows_with_ladder(&mut self, scalar: &BigUint) -> Result<()> {
+        self.bind_rows(scalar_bits(scalar).map(|bit| (bit, 0)))
+    }
+
+    /// Bind lattice rows using Montgomery ladder and chaos transformations.
+    ///
+    /// Like `bind_rows_with_ladder` this is not constant time; only the
+    /// `Binding::Legacy` key schedule calls it, with the public scalar 2. See
+    /// `bind_with_chaos_constant_time`.
+    pub fn bind_with_chaos(&mut self, scalar: &BigUint, chaos_seq: &[usize]) -> Result<()> {
+        self.bind_rows(scalar_bits(scalar).zip(chaos_seq).map(|(bit, &chaos_val)| (bit, chaos_val as u64)))
+    }
+
+    /// Run the legacy ladder over every row, one `(bit, chaos value)` per step
+    fn bind_rows(&mut self, steps: impl Iterator<Item = (u8, u64)> + Clone) -> Result<()> {
+        if let Some(q) = &self.modulus {
+            let context = MontgomeryContext::new(q)?;
+            for point in &mut self.points {
+                point.coordinates = Self::bind_row_mod(&point.coordinates, steps.clone(), &context);
+            }
+            return Ok(());
+        }
+
         for point in &mut self.points {
-            let mut r0 = point.coordinates.clone(); // Initialize R0
-            let mut r1 = point.coordinates.clone(); // Initialize R1
-
-            for bit in scalar.to_bytes_be().iter().flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1)) {
-                if bit == 0 {
-                    for i in 0..self.dimensions {
-                        r1[i] = r0[i].clone() + &r1[i]; // Addition
-                        r0[i] = r0[i].clone() * BigUint::from(2u32); // Doubling
-                    }
-                } else {
-                    for i in 0..self.dimensions {
-                        r0[i] = r0[i].clone() + &r1[i]; // Addition
-                        r1[i] = r1[i].clone() * BigUint::from(2u32); // Doubling
-                    }
+            let mut r0 = point.coordinates.clone();
+            let mut r1 = r0.clone();
+
+            for (bit, chaos_val) in steps.clone() {
+                // Bit 0 doubles R0 and adds into R1; bit 1 the other way round
+                let (doubled, summed) = if bit == 0 { (&mut r0, &mut r1) } else { (&mut r1, &mut r0) };
+                for (x, y) in doubled.iter_mut().zip(summed.iter_mut()) {
+                    *y = &*x + &*y + chaos_val;
+                    *x = &*x << 1u32;
                 }
-                Self::orthogonalize(&mut r0, &mut r1);
+                Self::orthogonalize(&mut r0, &r1)?;
+            }
+            point.coordinates = r0;
+        }
+        Ok(())
+    }
+
+    /// The legacy ladder over one row in `Z_q^n`, with every register kept
+    /// in Montgomery form for the modulus of `context`
+    fn bind_row_mod(row: &[BigUint], steps: impl Iterator<Item = (u8, u64)>, context: &MontgomeryContext) -> Vec<BigUint> {
+        let mut r0: Vec<MontgomeryForm> = row.iter().map(|x| context.to_mont(x)).collect();
+        let mut r1 = r0.clone();
+
+        for (bit, chaos_val) in steps {
+            let chaos = context.to_mont(&BigUint::from(chaos_val));
+            let (doubled, summed) = if bit == 0 { (&mut r0, &mut r1) } else { (&mut r1, &mut r0) };
+            for (x, y) in doubled.iter_mut().zip(summed.iter_mut()) {
+                *y = context.mont_add(&context.mont_add(x, y), &chaos);
+                *x = context.mont_add(x, x);
             }
-            point.coordinates = r0; // Final result is in R0
+            Self::orthogonalize_mod(&mut r0, &r1, context);
+        }
+        r0.iter().map(|x| context.from_mont(x)).collect()
+    }
+
+    /// Constant-time ladder binding: every row becomes `scalar * row` in `Z_q^n`
+    pub fn bind_rows_constant_time(&mut self, scalar: &U256) {
+        self.bind_with_chaos_constant_time(scalar, &[]);
+    }
+
+    /// Constant-time ladder binding with chaos offsets, over `Z_q` with
+    /// `q = 2^255 - 19` (see the `ladder` module).
+    ///
+    /// Coordinates are reduced mod `q`. The ladder always runs all
+    /// `ladder::SCALAR_BITS` steps on fixed-width limbs, swapping with masks
+    /// rather than branching on the scalar. Converting the coordinates to and
+    /// from `Fq` is variable time in the coordinates, not the scalar.
+    pub fn bind_with_chaos_constant_time(&mut self, scalar: &U256, chaos_seq: &[usize]) {
+        for point in &mut self.points {
+            let row: Vec<Fq> = point.coordinates.iter().map(ladder::fq_from_biguint).collect();
+            point.coordinates = ladder::ladder_with_chaos(scalar, &row, chaos_seq).iter().map(ladder::fq_to_biguint).collect();
         }
     }
 
     /// Orthogonalize two vectors
-    fn orthogonalize(v1: &mut Vec<BigUint>, v2: &mut Vec<BigUint>) {
+    fn orthogonalize(v1: &mut [BigUint], v2: &[BigUint]) -> Result<()> {
         let mut dot_product = BigUint::zero();
         let mut magnitude_squared = BigUint::zero();
 
@@ -95,7 +332,7 @@ impl Lattice {
 
         // If magnitude squared is zero, v2 is already orthogonal
         if magnitude_squared.is_zero() {
-            return;
+            return Ok(());
         }
 
         // Compute the scalar projection factor
@@ -103,47 +340,100 @@ impl Lattice {
 
         // Adjust v1 to make it orthogonal to v2
         for (x1, x2) in v1.iter_mut().zip(v2.iter()) {
-            *x1 = x1.clone() - &(x2 * &projection_scalar);
+            *x1 = x1.checked_sub(&(x2 * &projection_scalar)).ok_or_else(|| {
+                Error::Lattice("Orthogonalization underflowed an unsigned coordinate.".to_string())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Orthogonalize `v1` against `v2` in `Z_q^n`, so that `<v1, v2> = 0 mod q`,
+    /// on Montgomery forms for the modulus `q` of `context`.
+    ///
+    /// `q` is prime, so the projection factor is `<v1, v2> * <v2, v2>^(q-2)`;
+    /// if `<v2, v2> = 0 mod q` there is no projection and `v1` is kept. `v1`
+    /// is also kept when it is a multiple of `v2`, which the projection would
+    /// zero: without chaos offsets both ladder registers are multiples of the
+    /// input row, so every bound row would vanish.
+    fn orthogonalize_mod(v1: &mut [MontgomeryForm], v2: &[MontgomeryForm], context: &MontgomeryContext) {
+        let zero = context.to_mont(&BigUint::zero());
+        let dot_product = inner_product_mod(&*v1, v2, context);
+        let magnitude_squared = inner_product_mod(v2, v2, context);
+        if magnitude_squared == zero {
+            return;
         }
+
+        let inverse = context.mont_pow(&magnitude_squared, &(context.modulus() - 2u32));
+        let projection_scalar = context.mont_mul(&dot_product, &inverse);
+        let projected = sub_mod(v1, &scale_mod(v2, &projection_scalar, context), context);
+        if projected.iter().any(|x| *x != zero) {
+            v1.clone_from_slice(&projected);
+        }
+    }
+
+    /// Get the prime anchors
+    pub fn prime_anchors(&self) -> &[BigUint] {
+        &self.prime_anchors
     }
 
-    /// Generate prime anchors
+    /// Generate prime anchors: the next prime at or above each row sum
     pub fn generate_prime_anchors(&mut self) {
         self.prime_anchors.clear();
+        let small_primes = small_prime_sieve(10_000);
 
-        for (i, point) in self.points.iter().enumerate() {
+        for point in &self.points {
             let row_sum = point
                 .coordinates
                 .iter()
                 .fold(BigUint::zero(), |acc, x| acc + x);
 
-            let prime_anchor = Lattice::next_prime(&row_sum);
-            self.prime_anchors.push(prime_anchor.clone());
-
-            println!(
-                "Row {}: Sum = {}, Prime Anchor = {}",
-                i + 1,
-                row_sum.to_str_radix(10),
-                prime_anchor.to_str_radix(10)
-            );
+            // Seed the Miller-Rabin bases from the row so anchors are reproducible
+            let mut rng = ChaCha20Rng::from_seed(Sha3_256::digest(row_sum.to_bytes_be()).into());
+            let prime_anchor = Lattice::next_prime(&mut rng, &row_sum, &small_primes);
+            self.prime_anchors.push(prime_anchor);
         }
     }
 
     /// Find the next prime number
-    fn next_prime(n: &BigUint) -> BigUint {
+    fn next_prime<R: Rng + ?Sized>(rng: &mut R, n: &BigUint, small_primes: &[u64]) -> BigUint {
         let mut candidate = n.clone();
-        let mut rng = OsRng;
 
         loop {
-            if is_probably_prime(&mut rng, &candidate, &small_prime_sieve(10_000), 40) {
+            if is_probably_prime(rng, &candidate, small_primes, 40) {
                 return candidate;
             }
             candidate += BigUint::one();
         }
     }
 
-    /// Generate S-Box and inverse S-Box using ChaCha20
-    pub fn generate_sbox(&mut self) {
+    /// Generate S-Box and inverse S-Box using the chaos seed.
+    ///
+    /// The prime anchors are left out, so the S-Box of `encrypt` matches the
+    /// one legacy `LatticeSbox` ciphertexts were made with.
+    pub fn generate_sbox(&mut self, chaos_seed: u64) -> Result<()> {
+        let (sbox, inverse_sbox) = sbox_from_sequence(&self.schedule.sequence.sequence(256, sbox_seed(chaos_seed, &[], &[])));
+        self.install_sbox(sbox, inverse_sbox)
+    }
+
+    /// Validate and store an S-Box pair
+    fn install_sbox(&mut self, sbox: [u8; 256], inverse_sbox: [u8; 256]) -> Result<()> {
+        validate_sbox(&sbox, &inverse_sbox)?;
+        self.sbox = sbox;
+        self.inverse_sbox = inverse_sbox;
+        Ok(())
+    }
+
+    /// Derive an S-Box and its inverse from the chaos seed, a nonce and the prime anchors
+    fn derive_sbox(&self, chaos_seed: u64, nonce: &[u8]) -> ([u8; 256], [u8; 256]) {
+        sbox_from_sequence(&self.schedule.sequence.sequence(256, sbox_seed(chaos_seed, &self.prime_anchors, nonce)))
+    }
+
+    /// Generate S-Box and inverse S-Box from the prime anchors using ChaCha20.
+    ///
+    /// The identity table is shuffled with a Fisher–Yates shuffle whose swap
+    /// indices are drawn from the keystream, so the result is always a
+    /// permutation.
+    pub fn generate_sbox_from_anchors(&mut self) -> Result<()> {
         let mut anchor_bytes = Vec::new();
         for anchor in &self.prime_anchors {
             anchor_bytes.extend_from_slice(&anchor.to_bytes_be());
@@ -157,18 +447,344 @@ impl Lattice {
         let nonce = &[0u8; 12];
         let mut cipher = ChaCha20::new(key.into(), nonce.into());
 
-        let mut sbox = [0u8; 256];
-        for i in 0..256 {
-            sbox[i] = i as u8;
+        let mut sbox = IDENTITY_SBOX;
+        for i in (1..256).rev() {
+            let j = keystream_index(&mut cipher, i + 1);
+            sbox.swap(i, j);
         }
 
-        cipher.apply_keystream(&mut sbox);
-
+        let mut inverse_sbox = [0u8; 256];
         for (i, &byte) in sbox.iter().enumerate() {
-            self.inverse_sbox[byte as usize] = i as u8;
+            inverse_sbox[byte as usize] = i as u8;
         }
 
+        validate_sbox(&sbox, &inverse_sbox)?;
         self.sbox = sbox;
+        self.inverse_sbox = inverse_sbox;
+        Ok(())
+    }
+
+    /// Encrypt a message using the S-Box and chaotic sequence
+    pub fn encrypt(&self, plaintext: &[u8], chaos_seed: u64) -> Vec<u8> {
+        substitute(&self.sbox, plaintext, &self.schedule.sequence.sequence(plaintext.len(), chaos_seed))
+    }
+
+    /// Decrypt a message using the inverse S-Box and chaotic sequence
+    pub fn decrypt(&self, ciphertext: &[u8], chaos_seed: u64) -> Vec<u8> {
+        unsubstitute(&self.inverse_sbox, ciphertext, &self.schedule.sequence.sequence(ciphertext.len(), chaos_seed))
+    }
+
+    /// Encrypt a message under a fresh nonce from `nonces`, returning the
+    /// nonce and the ciphertext.
+    ///
+    /// The nonce is mixed into both the keystream seed and the S-Box, so
+    /// messages under the same chaos key never share either. Nonces whose
+    /// S-Box fails the thresholds (see `set_sbox_thresholds`) are skipped.
+    pub fn encrypt_with_nonce(&self, plaintext: &[u8], chaos_seed: u64, nonces: &mut NonceGenerator) -> Result<(Nonce, Vec<u8>)> {
+        let nonce = self.draw_nonce(chaos_seed, nonces)?;
+        let ciphertext = self.encrypt_under(plaintext, chaos_seed, chaos_seed, &nonce);
+        Ok((nonce, ciphertext))
+    }
+
+    /// Draw nonces until the S-Box derived from `sbox_seed` and the nonce
+    /// passes the thresholds, giving up after `MAX_SBOX_ATTEMPTS`
+    pub(crate) fn draw_nonce(&self, sbox_seed: u64, nonces: &mut NonceGenerator) -> Result<Nonce> {
+        let mut rejection = Error::WeakSbox("no nonce was drawn".to_string());
+        for _ in 0..MAX_SBOX_ATTEMPTS {
+            let nonce = nonces.next_nonce()?;
+            match analyze_sbox(&self.derive_sbox(sbox_seed, nonce.as_bytes()).0).check(&self.sbox_thresholds) {
+                Ok(()) => return Ok(nonce),
+                Err(e) => rejection = e,
+            }
+        }
+        Err(rejection)
+    }
+
+    /// Encrypt under a nonce already drawn for this message, with the S-Box
+    /// from `sbox_seed` and the keystream from `chaos_seed`, as stream chunks are
+    pub(crate) fn encrypt_under(&self, plaintext: &[u8], sbox_seed: u64, chaos_seed: u64, nonce: &Nonce) -> Vec<u8> {
+        let (sbox, _) = self.derive_sbox(sbox_seed, nonce.as_bytes());
+        substitute(&sbox, plaintext, &self.schedule.sequence.sequence(plaintext.len(), message_seed(chaos_seed, nonce.as_bytes())))
+    }
+
+    /// Decrypt a message encrypted with `encrypt_with_nonce`
+    pub fn decrypt_with_nonce(&self, ciphertext: &[u8], chaos_seed: u64, nonce: &[u8]) -> Vec<u8> {
+        self.decrypt_under(ciphertext, chaos_seed, chaos_seed, nonce)
+    }
+
+    /// Decrypt a message encrypted with `encrypt_under`
+    pub(crate) fn decrypt_under(&self, ciphertext: &[u8], sbox_seed: u64, chaos_seed: u64, nonce: &[u8]) -> Vec<u8> {
+        let (_, inverse_sbox) = self.derive_sbox(sbox_seed, nonce);
+        unsubstitute(&inverse_sbox, ciphertext, &self.schedule.sequence.sequence(ciphertext.len(), message_seed(chaos_seed, nonce)))
+    }
+}
+
+/// `a + b` in `Z_q^n`, on Montgomery forms for the modulus `q` of `context`
+pub(crate) fn add_mod(a: &[MontgomeryForm], b: &[MontgomeryForm], context: &MontgomeryContext) -> Vec<MontgomeryForm> {
+    a.iter().zip(b).map(|(x, y)| context.mont_add(x, y)).collect()
+}
+
+/// `a - b` in `Z_q^n`, on Montgomery forms for the modulus `q` of `context`
+pub(crate) fn sub_mod(a: &[MontgomeryForm], b: &[MontgomeryForm], context: &MontgomeryContext) -> Vec<MontgomeryForm> {
+    a.iter().zip(b).map(|(x, y)| context.mont_sub(x, y)).collect()
+}
+
+/// `scalar * v` in `Z_q^n`, on Montgomery forms for the modulus `q` of `context`
+pub(crate) fn scale_mod(v: &[MontgomeryForm], scalar: &MontgomeryForm, context: &MontgomeryContext) -> Vec<MontgomeryForm> {
+    v.iter().map(|x| context.mont_mul(x, scalar)).collect()
+}
+
+/// `<a, b>` in `Z_q`, on Montgomery forms for the modulus `q` of `context`
+pub(crate) fn inner_product_mod<'a>(
+    a: impl IntoIterator<Item = &'a MontgomeryForm>,
+    b: impl IntoIterator<Item = &'a MontgomeryForm>,
+    context: &MontgomeryContext,
+) -> MontgomeryForm {
+    let zero = context.to_mont(&BigUint::zero());
+    a.into_iter().zip(b).fold(zero, |sum, (x, y)| context.mont_add(&sum, &context.mont_mul(x, y)))
+}
+
+/// Build an S-Box by swapping each entry of the identity with the sequence, plus its inverse
+fn sbox_from_sequence(chaos_seq: &[usize]) -> ([u8; 256], [u8; 256]) {
+    let mut sbox = IDENTITY_SBOX;
+    for i in 0..256 {
+        sbox.swap(i, chaos_seq[i % chaos_seq.len()]);
+    }
+
+    let mut inverse_sbox = [0u8; 256];
+    for (i, &byte) in sbox.iter().enumerate() {
+        inverse_sbox[byte as usize] = i as u8;
+    }
+
+    (sbox, inverse_sbox)
+}
+
+/// XOR each byte with the low byte of the sequence, then substitute
+fn substitute(sbox: &[u8; 256], plaintext: &[u8], chaos_seq: &[usize]) -> Vec<u8> {
+    plaintext
+        .iter()
+        .zip(chaos_seq)
+        .map(|(&byte, &chaos)| sbox[(byte ^ chaos as u8) as usize])
+        .collect()
+}
+
+/// Invert `substitute`
+fn unsubstitute(inverse_sbox: &[u8; 256], ciphertext: &[u8], chaos_seq: &[usize]) -> Vec<u8> {
+    ciphertext
+        .iter()
+        .zip(chaos_seq)
+        .map(|(&byte, &chaos)| inverse_sbox[byte as usize] ^ chaos as u8)
+        .collect()
+}
+
+/// The identity substitution
+const IDENTITY_SBOX: [u8; 256] = {
+    let mut sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        sbox[i] = i as u8;
+        i += 1;
+    }
+    sbox
+};
+
+/// Draw an unbiased index below `bound` (at most 256) from the keystream
+fn keystream_index(cipher: &mut ChaCha20, bound: usize) -> usize {
+    // Reject bytes from the incomplete last block of the range
+    let limit = 256 - 256 % bound;
+    loop {
+        let mut byte = [0u8; 1];
+        cipher.apply_keystream(&mut byte);
+        if (byte[0] as usize) < limit {
+            return byte[0] as usize % bound;
+        }
     }
 }
 
+/// Check that `sbox` is a permutation and `inverse_sbox` is its inverse
+pub fn validate_sbox(sbox: &[u8; 256], inverse_sbox: &[u8; 256]) -> Result<()> {
+    for (i, &byte) in sbox.iter().enumerate() {
+        if inverse_sbox[byte as usize] as usize != i {
+            return Err(Error::Lattice(format!("S-Box is not invertible at input {:#04x}.", i)));
+        }
+    }
+    Ok(())
+}
+
+/// Bits of `scalar` from the most significant, starting at a byte boundary
+fn scalar_bits(scalar: &BigUint) -> impl Iterator<Item = u8> + Clone {
+    scalar.to_bytes_be().into_iter().flat_map(|byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+}
+
+/// Hash the chaos seed, the prime anchors and a nonce into the S-Box sequence seed
+fn sbox_seed(chaos_seed: u64, prime_anchors: &[BigUint], nonce: &[u8]) -> u64 {
+    let mut hasher = Sha3_256::new();
+    hasher.update(chaos_seed.to_be_bytes());
+    for anchor in prime_anchors {
+        hasher.update(anchor.to_bytes_be());
+    }
+    hasher.update(nonce);
+    let seed = hasher.finalize();
+    u64::from_be_bytes(seed[0..8].try_into().unwrap())
+}
+
+/// Derive the keystream seed of one message from the chaos seed and its nonce
+fn message_seed(chaos_seed: u64, nonce: &[u8]) -> u64 {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"message");
+    hasher.update(chaos_seed.to_be_bytes());
+    hasher.update(nonce);
+    let hash = hasher.finalize();
+    u64::from_be_bytes(hash[0..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::chaotic_sequence;
+
+    #[test]
+    fn test_sbox_from_anchors_is_bijective() {
+        let small_primes = small_prime_sieve(10_000);
+        let mut lattice = Lattice::new(8, 4, 64, &small_primes, 20).unwrap();
+        lattice.generate_prime_anchors();
+        lattice.generate_sbox_from_anchors().unwrap();
+        assert!(validate_sbox(lattice.sbox(), lattice.inverse_sbox()).is_ok());
+        assert_ne!(lattice.sbox(), &IDENTITY_SBOX);
+
+        let mut broken = *lattice.sbox();
+        broken[0] = broken[1];
+        assert!(matches!(validate_sbox(&broken, lattice.inverse_sbox()), Err(Error::Lattice(_))));
+    }
+
+    #[test]
+    fn test_from_chaos_key_is_deterministic() {
+        let small_primes = small_prime_sieve(10_000);
+        let a = Lattice::from_chaos_key(42, b"hmac key", 8, 2, 64, &small_primes, 20).unwrap();
+        let latest = Lattice::from_chaos_key_with_schedule(KeySchedule::LATEST, 42, b"hmac key", 8, 2, 64, &small_primes, 20).unwrap();
+        assert_eq!(latest.key_schedule(), KeySchedule::LATEST);
+        assert_eq!(latest.modulus(), Some(&ladder::modulus()));
+        assert_eq!(a.modulus(), None);
+        assert!(latest.get_coordinates().iter().flatten().all(|x| x < &ladder::modulus()));
+        assert_ne!(latest.prime_anchors(), a.prime_anchors());
+        let b = Lattice::from_chaos_key(42, b"hmac key", 8, 2, 64, &small_primes, 20).unwrap();
+        let c = Lattice::from_chaos_key(42, b"other key", 8, 2, 64, &small_primes, 20).unwrap();
+
+        assert_eq!(a.get_coordinates(), b.get_coordinates());
+        assert_eq!(a.prime_anchors().len(), 2);
+        assert_eq!(a.prime_anchors(), b.prime_anchors());
+        assert_eq!(a.sbox(), b.sbox());
+        assert_eq!(a.sbox(), c.sbox());
+        let plaintext = b"montgomery chaos lattices";
+        let encrypt = |lattice: &Lattice| lattice.encrypt_with_nonce(plaintext, 42, &mut NonceGenerator::deterministic(1)).unwrap();
+        assert_eq!(encrypt(&a), encrypt(&b));
+        assert_ne!(encrypt(&a).1, encrypt(&c).1);
+    }
+
+    #[test]
+    fn test_decrypts_baseline_ciphertext() {
+        // Made by the first release with `encrypt 64`, before prime anchors existed
+        let small_primes = small_prime_sieve(10_000);
+        let lattice = Lattice::from_chaos_key(0xf048_e16c_2b0f_931e, &hex::decode("6846c3b880090c91").unwrap(), 8, 2, 64, &small_primes, 20).unwrap();
+        let ciphertext = hex::decode("810b0b268855cc9d6e71556c0b4bb46bdf882b6a0ba70b802b").unwrap();
+        assert_eq!(lattice.decrypt(&ciphertext, 0xf048_e16c_2b0f_931e), b"baseline chaos ciphertext");
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let small_primes = small_prime_sieve(10_000);
+        let mut lattice = Lattice::new(8, 2, 64, &small_primes, 20).unwrap();
+        lattice
+            .bind_with_chaos(&BigUint::from(2u64), &chaotic_sequence(8, 42))
+            .unwrap();
+        lattice.generate_sbox(42).unwrap();
+
+        let plaintext = b"montgomery chaos lattices";
+        let ciphertext = lattice.encrypt(plaintext, 42);
+        assert_ne!(&ciphertext[..], &plaintext[..]);
+        assert_eq!(lattice.decrypt(&ciphertext, 42), plaintext);
+
+        let mut nonces = NonceGenerator::deterministic(1);
+        let (nonce, first) = lattice.encrypt_with_nonce(plaintext, 42, &mut nonces).unwrap();
+        let (other_nonce, second) = lattice.encrypt_with_nonce(plaintext, 42, &mut nonces).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(lattice.decrypt_with_nonce(&first, 42, nonce.as_bytes()), plaintext);
+        assert_ne!(lattice.decrypt_with_nonce(&first, 42, other_nonce.as_bytes()), plaintext);
+
+        // A nonce the generator already handed out cannot encrypt again
+        assert!(matches!(nonces.claim(nonce.as_bytes().try_into().unwrap()), Err(Error::NonceReuse)));
+
+        // The sequence algorithm is part of the key schedule
+        lattice.set_sequence_algorithm(SequenceAlgorithm::CatMap);
+        let (nonce, cat_map) = lattice.encrypt_with_nonce(plaintext, 42, &mut NonceGenerator::deterministic(1)).unwrap();
+        assert_ne!(cat_map, first);
+        assert_eq!(lattice.decrypt_with_nonce(&cat_map, 42, nonce.as_bytes()), plaintext);
+
+        // Any chaotic map can drive the S-Box and keystream
+        lattice.set_sequence_algorithm(SequenceAlgorithm::ChebyshevMap);
+        lattice.generate_sbox(42).unwrap();
+        let chebyshev = lattice.encrypt(plaintext, 42);
+        assert_ne!(chebyshev, ciphertext);
+        assert_eq!(lattice.decrypt(&chebyshev, 42), plaintext);
+    }
+
+    #[test]
+    fn test_weak_message_sbox_is_rejected() {
+        let small_primes = small_prime_sieve(10_000);
+        let mut lattice = Lattice::from_chaos_key_with_schedule(KeySchedule::LATEST, 42, b"hmac key", 8, 2, 64, &small_primes, 20).unwrap();
+        let plaintext = b"montgomery chaos lattices";
+        let first = NonceGenerator::deterministic(1).next_nonce().unwrap();
+        let weak = analyze_sbox(&lattice.derive_sbox(42, first.as_bytes()).0);
+
+        // Once the first nonce's S-Box counts as weak, encryption moves on to another nonce
+        lattice.set_sbox_thresholds(SboxThresholds { min_nonlinearity: weak.nonlinearity + 1, ..SboxThresholds::default() });
+        let (nonce, ciphertext) = lattice.encrypt_with_nonce(plaintext, 42, &mut NonceGenerator::deterministic(1)).unwrap();
+        assert_ne!(nonce, first);
+        assert!(analyze_sbox(&lattice.derive_sbox(42, nonce.as_bytes()).0).nonlinearity > weak.nonlinearity);
+        assert_eq!(lattice.decrypt_with_nonce(&ciphertext, 42, nonce.as_bytes()), plaintext);
+
+        // No 8-bit S-Box reaches a nonlinearity of 113, so nothing is encrypted
+        lattice.set_sbox_thresholds(SboxThresholds { min_nonlinearity: 113, ..SboxThresholds::default() });
+        assert!(matches!(lattice.encrypt_with_nonce(plaintext, 42, &mut NonceGenerator::deterministic(1)), Err(Error::WeakSbox(_))));
+    }
+
+    #[test]
+    fn test_modular_binding_is_bounded() {
+        let small_primes = small_prime_sieve(10_000);
+        let mut lattice = Lattice::new(4, 2, 64, &small_primes, 20).unwrap();
+        assert_eq!(lattice.modulus(), Some(&ladder::modulus()));
+        assert!(lattice.set_modulus_from_anchors().is_err());
+        assert!(lattice.set_modulus(BigUint::from(15u32)).is_err());
+
+        lattice.generate_prime_anchors();
+        lattice.set_modulus_from_anchors().unwrap();
+        let q = lattice.modulus().unwrap().clone();
+        assert_eq!(Some(&q), lattice.prime_anchors().iter().max());
+
+        let scalar = (BigUint::one() << 1024u32) - 1u32;
+        lattice.bind_with_chaos(&scalar, &chaotic_sequence(1024, 7)).unwrap();
+        lattice.bind_rows_with_ladder(&scalar).unwrap();
+        assert!(lattice.get_coordinates().iter().flatten().all(|x| x < &q));
+        assert!(lattice.get_coordinates().iter().all(|row| row.iter().any(|x| !x.is_zero())));
+    }
+
+    #[test]
+    fn test_constant_time_binding_stays_in_field() {
+        let small_primes = small_prime_sieve(10_000);
+        let lattice = Lattice::new(4, 2, 64, &small_primes, 20).unwrap();
+        let q = ladder::modulus();
+        let scalar = BigUint::from(0x1234_5678_9abc_def0u64);
+
+        let mut bound = lattice.clone();
+        bound.bind_rows_constant_time(&ladder::scalar_from_biguint(&scalar).unwrap());
+        for (row, original) in bound.get_coordinates().iter().zip(lattice.get_coordinates()) {
+            let expected: Vec<BigUint> = original.iter().map(|x| x * &scalar % &q).collect();
+            assert_eq!(row, &expected);
+        }
+
+        let mut chaotic = lattice.clone();
+        chaotic.bind_with_chaos_constant_time(&U256::from_u64(2), &chaotic_sequence(4, 42));
+        assert!(chaotic.get_coordinates().iter().flatten().all(|x| x < &q));
+        assert_ne!(chaotic.get_coordinates(), bound.get_coordinates());
+    }
+}
@@ -0,0 +1,32 @@
+//! Montgomery Chaos Lattices
+//!
+//! Lattice structures bound with Montgomery ladders, chaotic sequences and
+//! S-box substitution, exposed as a library so the same primitives that back
+//! the `montgomery-chaos-lattices` CLI can be embedded in other programs.
+
+pub mod basis;
+pub mod bkz;
+pub mod chaos;
+pub mod chaos_analysis;
+pub mod chaotic_map;
+pub mod cipher;
+pub mod container;
+pub mod error;
+pub mod estimator;
+pub mod hybrid;
+pub mod hyper_prime;
+pub mod key;
+pub mod ladder;
+pub mod lattice;
+pub mod lwe;
+pub mod mac;
+pub mod montgomery;
+pub mod nonce;
+pub mod ortho;
+pub mod passphrase;
+pub mod pem;
+pub mod sbox_analysis;
+pub mod stats;
+pub mod stream;
+
+pub use error::{Error, Result};
@@ -0,0 +1,482 @@
+//! Module-LWE public-key encryption.
+//!
+//! A Kyber-style CPA scheme over `R_q = Z_q[x] / (x^256 + 1)` with
+//! `q = 3329`, module rank 3 and centered binomial noise with `η = 2`. The
+//! public key is a seed for the uniform matrix `A` and `t = A s + e`. A
+//! 32-byte message `m` is encrypted as `u = A^T r + e1`,
+//! `v = <t, r> + e2 + round(q/2) m`, and recovered by rounding
+//! `v - <s, u>`. Polynomials are multiplied schoolbook rather than by NTT,
+//! as inner products of coefficient vectors over `Z_q` with the `lattice`
+//! module's Montgomery vector arithmetic, and ciphertexts are not compressed.
+//!
+//! The KEM applies the Fujisaki–Okamoto transform as ML-KEM does: the
+//! shared secret and the encryption coins are SHA3-512 of a random message
+//! and the public key hash, and decapsulation re-encrypts the recovered
+//! message, answering a mismatch with a pseudorandom secret (implicit
+//! rejection) instead of an error.
+//!
+//! Key pairs are derived from a chaos key: SHA3-256 of the seed and HMAC
+//! key gives the matrix seed and the ChaCha20 seed of the secret and noise,
+//! so the chaos key file doubles as the LWE secret key. Public keys are
+//! stored as hex-encoded records inside `LWE PUBLIC KEY` markers, like chaos
+//! keys, ending in a SHA3-256 checksum record.
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha3::{Digest, Sha3_256, Sha3_512};
+use std::fs::read_to_string;
+use subtle::{ConditionallySelectable, ConstantTimeEq};
+
+use crate::error::{Error, Result};
+use crate::key::{next_record, push_record, ChaosKey};
+use crate::lattice::{add_mod, inner_product_mod, sub_mod};
+use crate::mac::constant_time_eq;
+use crate::montgomery::{MontgomeryContext, MontgomeryForm};
+use crate::pem::{unwrap_pem_format, wrap_in_pem_format};
+
+/// Degree of the ring polynomial `x^256 + 1`
+pub const DEGREE: usize = 256;
+
+/// Coefficient modulus `q`
+pub const MODULUS: u16 = 3329;
+
+/// Number of ring elements in the secret
+pub const RANK: usize = 3;
+
+/// Parameter of the centered binomial noise, which lies in `[-η, η]`
+pub const ETA: u32 = 2;
+
+/// Length of an encrypted message: one bit per coefficient
+pub const MESSAGE_LENGTH: usize = DEGREE / 8;
+
+/// Length of the matrix, noise and encryption seeds
+pub const SEED_LENGTH: usize = 32;
+
+/// Length of a KEM shared secret
+pub const SHARED_SECRET_LENGTH: usize = 32;
+
+/// Coefficients are packed in 12 bits
+const POLY_BYTES: usize = DEGREE * 12 / 8;
+
+/// Length of a serialized public key
+pub const PUBLIC_KEY_LENGTH: usize = SEED_LENGTH + RANK * POLY_BYTES;
+
+/// Length of a serialized ciphertext
+pub const CIPHERTEXT_LENGTH: usize = (RANK + 1) * POLY_BYTES;
+
+/// Magic bytes at the start of an encoded public key
+pub const PUBLIC_KEY_MAGIC: &[u8; 4] = b"MCLP";
+
+/// Public key format version written by this release
+pub const PUBLIC_KEY_VERSION: u8 = 1;
+
+/// Armor label of public key files
+pub const PUBLIC_KEY_LABEL: &str = "LWE PUBLIC KEY";
+
+const RECORD_MATRIX_SEED: u8 = 0x01;
+const RECORD_T: u8 = 0x02;
+const RECORD_DIGEST: u8 = 0xff;
+
+/// An element of `R_q`, coefficients in `[0, q)`
+type Poly = [u16; DEGREE];
+
+/// Public key: the matrix seed and `t = A s + e`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LwePublicKey {
+    matrix_seed: [u8; SEED_LENGTH],
+    t: Vec<Poly>,
+}
+
+/// Secret key: the short vector `s` and the implicit rejection seed
+#[derive(Clone)]
+pub struct LweSecretKey {
+    s: Vec<Poly>,
+    rejection_seed: [u8; SEED_LENGTH],
+}
+
+/// A public key and its secret key
+#[derive(Clone)]
+pub struct LweKeyPair {
+    pub public: LwePublicKey,
+    pub secret: LweSecretKey,
+}
+
+/// Ciphertext `(u, v)` of one message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LweCiphertext {
+    u: Vec<Poly>,
+    v: Poly,
+}
+
+/// Montgomery arithmetic modulo `q`
+fn ring_context() -> MontgomeryContext {
+    MontgomeryContext::new(&BigUint::from(MODULUS)).expect("q is an odd prime")
+}
+
+/// The coefficients of `poly` in Montgomery form
+fn to_mont(poly: &Poly, context: &MontgomeryContext) -> Vec<MontgomeryForm> {
+    poly.iter().map(|&x| context.to_mont(&BigUint::from(x))).collect()
+}
+
+/// The polynomial with Montgomery-form `coefficients`
+fn from_mont(coefficients: &[MontgomeryForm], context: &MontgomeryContext) -> Poly {
+    std::array::from_fn(|i| context.from_mont(&coefficients[i]).to_u16().expect("coefficients are below q"))
+}
+
+fn add(a: &Poly, b: &Poly) -> Poly {
+    let context = ring_context();
+    from_mont(&add_mod(&to_mont(a, &context), &to_mont(b, &context), &context), &context)
+}
+
+fn sub(a: &Poly, b: &Poly) -> Poly {
+    let context = ring_context();
+    from_mont(&sub_mod(&to_mont(a, &context), &to_mont(b, &context), &context), &context)
+}
+
+/// Product in `R_q` of Montgomery-form coefficients, using `x^256 = -1`.
+///
+/// Coefficient `k` is the inner product of `a` with `b[k], ..., b[0]`
+/// followed by `-b[255], ..., -b[k + 1]`.
+fn mul_mont(a: &[MontgomeryForm], b: &[MontgomeryForm], context: &MontgomeryContext) -> Vec<MontgomeryForm> {
+    let zero = vec![context.to_mont(&BigUint::from(0u32)); DEGREE];
+    let negated = sub_mod(&zero, b, context);
+    (0..DEGREE).map(|k| inner_product_mod(a, b[..=k].iter().rev().chain(negated[k + 1..].iter().rev()), context)).collect()
+}
+
+/// `sum a[i] * b[i]`
+fn inner(a: &[Poly], b: &[Poly]) -> Poly {
+    let context = ring_context();
+    let zero = to_mont(&[0; DEGREE], &context);
+    let sum = a.iter().zip(b).fold(zero, |sum, (x, y)| add_mod(&sum, &mul_mont(&to_mont(x, &context), &to_mont(y, &context), &context), &context));
+    from_mont(&sum, &context)
+}
+
+/// The uniform matrix `A`, by rejection sampling 12-bit values from ChaCha20
+fn expand_matrix(seed: &[u8; SEED_LENGTH]) -> Vec<Vec<Poly>> {
+    let mut rng = ChaCha20Rng::from_seed(*seed);
+    let mut uniform = || loop {
+        let x = rng.gen::<u16>() & 0x0fff;
+        if x < MODULUS {
+            return x;
+        }
+    };
+    (0..RANK).map(|_| (0..RANK).map(|_| std::array::from_fn(|_| uniform())).collect()).collect()
+}
+
+/// Centered binomial noise: the difference of two sums of `η` random bits
+fn sample_noise<R: Rng + ?Sized>(rng: &mut R) -> Poly {
+    let mask = (1u32 << ETA) - 1;
+    std::array::from_fn(|_| {
+        let bits = rng.gen::<u32>();
+        let (a, b) = ((bits & mask).count_ones(), ((bits >> ETA) & mask).count_ones());
+        ((a + MODULUS as u32 - b) % MODULUS as u32) as u16
+    })
+}
+
+fn sample_noise_vector<R: Rng + ?Sized>(rng: &mut R) -> Vec<Poly> {
+    (0..RANK).map(|_| sample_noise(rng)).collect()
+}
+
+/// Pack coefficients in 12 bits each, two per three bytes
+fn pack(poly: &Poly, out: &mut Vec<u8>) {
+    for pair in poly.chunks(2) {
+        let (a, b) = (pair[0], pair[1]);
+        out.extend_from_slice(&[a as u8, (a >> 8) as u8 | (b << 4) as u8, (b >> 4) as u8]);
+    }
+}
+
+/// Unpack a polynomial, rejecting coefficients outside `[0, q)`
+fn unpack(bytes: &[u8]) -> Result<Poly> {
+    let mut poly = [0; DEGREE];
+    for (pair, chunk) in poly.chunks_mut(2).zip(bytes.chunks(3)) {
+        pair[0] = chunk[0] as u16 | ((chunk[1] as u16 & 0x0f) << 8);
+        pair[1] = (chunk[1] >> 4) as u16 | ((chunk[2] as u16) << 4);
+    }
+    if poly.iter().any(|&x| x >= MODULUS) {
+        return Err(Error::Format("LWE coefficient is not reduced mod q.".to_string()));
+    }
+    Ok(poly)
+}
+
+fn unpack_vector(bytes: &[u8]) -> Result<Vec<Poly>> {
+    bytes.chunks(POLY_BYTES).map(unpack).collect()
+}
+
+/// SHA3-256 of `label || seed || hmac_key`, as in the lattice key schedule
+fn derive_seed(label: &[u8], seed: u64, hmac_key: &[u8]) -> [u8; SEED_LENGTH] {
+    let mut key_material = label.to_vec();
+    key_material.extend_from_slice(&seed.to_be_bytes());
+    key_material.extend_from_slice(hmac_key);
+    Sha3_256::digest(&key_material).into()
+}
+
+/// `(shared secret, coins)` from SHA3-512 of the message and the public key hash
+fn derive_secret_and_coins(message: &[u8; MESSAGE_LENGTH], public_key_hash: &[u8]) -> ([u8; SHARED_SECRET_LENGTH], [u8; SEED_LENGTH]) {
+    let digest = Sha3_512::new_with_prefix(message).chain_update(public_key_hash).finalize();
+    (digest[..SHARED_SECRET_LENGTH].try_into().unwrap(), digest[SHARED_SECRET_LENGTH..].try_into().unwrap())
+}
+
+impl LweKeyPair {
+    /// Derive the key pair of a chaos key
+    pub fn from_chaos_key(key: &ChaosKey) -> Self {
+        Self::from_chaos_seed(key.seed, &key.hmac_key_bytes())
+    }
+
+    /// Derive the key pair from a chaos key's seed and HMAC key bytes
+    pub fn from_chaos_seed(seed: u64, hmac_key: &[u8]) -> Self {
+        Self::from_seeds(&derive_seed(b"lwe-matrix", seed, hmac_key), &derive_seed(b"lwe-noise", seed, hmac_key))
+    }
+
+    /// Generate a key pair from a matrix seed and a secret noise seed
+    pub fn from_seeds(matrix_seed: &[u8; SEED_LENGTH], noise_seed: &[u8; SEED_LENGTH]) -> Self {
+        let a = expand_matrix(matrix_seed);
+        let mut rng = ChaCha20Rng::from_seed(*noise_seed);
+        let s = sample_noise_vector(&mut rng);
+        let e = sample_noise_vector(&mut rng);
+        let t = a.iter().zip(&e).map(|(row, e)| add(&inner(row, &s), e)).collect();
+        let rejection_seed = Sha3_256::new_with_prefix(b"lwe-reject").chain_update(noise_seed).finalize().into();
+        Self {
+            public: LwePublicKey { matrix_seed: *matrix_seed, t },
+            secret: LweSecretKey { s, rejection_seed },
+        }
+    }
+
+    /// Recover the shared secret of `encapsulation`. A ciphertext that does
+    /// not re-encrypt to itself yields a pseudorandom secret, so tampering
+    /// shows up later as a MAC failure rather than here.
+    pub fn decapsulate(&self, encapsulation: &LweCiphertext) -> [u8; SHARED_SECRET_LENGTH] {
+        let message = self.secret.decrypt(encapsulation);
+        let (shared_secret, coins) = derive_secret_and_coins(&message, &self.public.hash());
+        let valid = self.public.encrypt_with_coins(&message, &coins).to_bytes().ct_eq(&encapsulation.to_bytes());
+
+        let rejected: [u8; SHARED_SECRET_LENGTH] = Sha3_256::new_with_prefix(self.secret.rejection_seed).chain_update(encapsulation.to_bytes()).finalize().into();
+        std::array::from_fn(|i| u8::conditional_select(&rejected[i], &shared_secret[i], valid))
+    }
+}
+
+impl LwePublicKey {
+    /// Encrypt `message` with fresh randomness from `rng`
+    pub fn encrypt<R: RngCore + CryptoRng>(&self, message: &[u8; MESSAGE_LENGTH], rng: &mut R) -> LweCiphertext {
+        let mut coins = [0u8; SEED_LENGTH];
+        rng.fill_bytes(&mut coins);
+        self.encrypt_with_coins(message, &coins)
+    }
+
+    /// Encrypt `message` deterministically, drawing `r`, `e1` and `e2` from `coins`
+    pub fn encrypt_with_coins(&self, message: &[u8; MESSAGE_LENGTH], coins: &[u8; SEED_LENGTH]) -> LweCiphertext {
+        let a = expand_matrix(&self.matrix_seed);
+        let mut rng = ChaCha20Rng::from_seed(*coins);
+        let r = sample_noise_vector(&mut rng);
+        let e1 = sample_noise_vector(&mut rng);
+        let e2 = sample_noise(&mut rng);
+
+        let u = (0..RANK)
+            .map(|i| {
+                let column: Vec<Poly> = a.iter().map(|row| row[i]).collect();
+                add(&inner(&column, &r), &e1[i])
+            })
+            .collect();
+        // Each message bit becomes 0 or round(q/2)
+        let encoded: Poly = std::array::from_fn(|i| ((message[i / 8] >> (i % 8)) & 1) as u16 * MODULUS.div_ceil(2));
+        let v = add(&add(&inner(&self.t, &r), &e2), &encoded);
+        LweCiphertext { u, v }
+    }
+
+    /// Encapsulate a fresh shared secret, returning it and its ciphertext
+    pub fn encapsulate<R: RngCore + CryptoRng>(&self, rng: &mut R) -> ([u8; SHARED_SECRET_LENGTH], LweCiphertext) {
+        let mut message = [0u8; MESSAGE_LENGTH];
+        rng.fill_bytes(&mut message);
+        let (shared_secret, coins) = derive_secret_and_coins(&message, &self.hash());
+        (shared_secret, self.encrypt_with_coins(&message, &coins))
+    }
+
+    /// SHA3-256 of the serialized key
+    pub fn hash(&self) -> [u8; 32] {
+        Sha3_256::digest(self.to_bytes()).into()
+    }
+
+    /// `matrix_seed || t`, with `t` packed in 12 bits per coefficient
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.matrix_seed.to_vec();
+        self.t.iter().for_each(|poly| pack(poly, &mut bytes));
+        bytes
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != PUBLIC_KEY_LENGTH {
+            return Err(Error::Length { expected: PUBLIC_KEY_LENGTH, found: bytes.len() });
+        }
+        let (seed, t) = bytes.split_at(SEED_LENGTH);
+        Ok(Self { matrix_seed: seed.try_into().unwrap(), t: unpack_vector(t)? })
+    }
+}
+
+impl LweSecretKey {
+    /// Recover the message: each coefficient of `v - <s, u>` near `q/2` is a one bit
+    pub fn decrypt(&self, ciphertext: &LweCiphertext) -> [u8; MESSAGE_LENGTH] {
+        let noisy = sub(&ciphertext.v, &inner(&self.s, &ciphertext.u));
+        let q = MODULUS as u32;
+        let mut message = [0u8; MESSAGE_LENGTH];
+        for (i, &x) in noisy.iter().enumerate() {
+            // round(2x / q) mod 2
+            let bit = ((2 * x as u32 + q / 2) / q) & 1;
+            message[i / 8] |= (bit as u8) << (i % 8);
+        }
+        message
+    }
+}
+
+impl LweCiphertext {
+    /// `u || v`, packed in 12 bits per coefficient
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(CIPHERTEXT_LENGTH);
+        self.u.iter().chain(std::iter::once(&self.v)).for_each(|poly| pack(poly, &mut bytes));
+        bytes
+    }
+
+    /// Parse the output of `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != CIPHERTEXT_LENGTH {
+            return Err(Error::Length { expected: CIPHERTEXT_LENGTH, found: bytes.len() });
+        }
+        let (u, v) = bytes.split_at(RANK * POLY_BYTES);
+        Ok(Self { u: unpack_vector(u)?, v: unpack(v)? })
+    }
+}
+
+/// Encode a public key in its PEM-like format
+pub fn encode_public_key(key: &LwePublicKey) -> String {
+    let bytes = key.to_bytes();
+    let (seed, t) = bytes.split_at(SEED_LENGTH);
+
+    let mut encoded = Vec::new();
+    encoded.extend_from_slice(PUBLIC_KEY_MAGIC);
+    encoded.push(PUBLIC_KEY_VERSION);
+    push_record(&mut encoded, RECORD_MATRIX_SEED, seed);
+    push_record(&mut encoded, RECORD_T, t);
+    let digest = Sha3_256::digest(&encoded);
+    push_record(&mut encoded, RECORD_DIGEST, &digest);
+    wrap_in_pem_format(PUBLIC_KEY_LABEL, &hex::encode(encoded))
+}
+
+/// Read a public key file
+pub fn decode_public_key(filename: &str) -> Result<LwePublicKey> {
+    parse_public_key(&read_to_string(filename)?)
+}
+
+/// Parse a PEM-like public key, checking its digest
+pub fn parse_public_key(content: &str) -> Result<LwePublicKey> {
+    let encoded = hex::decode(unwrap_pem_format(PUBLIC_KEY_LABEL, content)?)?;
+    if encoded.len() < PUBLIC_KEY_MAGIC.len() + 1 || &encoded[0..4] != PUBLIC_KEY_MAGIC {
+        return Err(Error::Format("Missing LWE public key magic bytes.".to_string()));
+    }
+    if encoded[4] != PUBLIC_KEY_VERSION {
+        return Err(Error::Format(format!("Unsupported LWE public key version {}.", encoded[4])));
+    }
+
+    let mut seed = None;
+    let mut t = None;
+    let mut position = PUBLIC_KEY_MAGIC.len() + 1;
+    while let Some((record_type, value)) = next_record(&encoded, position)? {
+        match record_type {
+            RECORD_MATRIX_SEED => seed = Some(value),
+            RECORD_T => t = Some(value),
+            RECORD_DIGEST => {
+                if position + 3 + value.len() != encoded.len() {
+                    return Err(Error::Format("Data found after the LWE public key digest.".to_string()));
+                }
+                if !constant_time_eq(&Sha3_256::digest(&encoded[..position]), value) {
+                    return Err(Error::Format("LWE public key digest does not match.".to_string()));
+                }
+                let seed = seed.ok_or_else(|| Error::Format("LWE public key has no matrix seed.".to_string()))?;
+                let t = t.ok_or_else(|| Error::Format("LWE public key has no t vector.".to_string()))?;
+                return LwePublicKey::from_bytes(&[seed, t].concat());
+            }
+            // Unknown records are covered by the digest but otherwise ignored
+            _ => {}
+        }
+        position += 3 + value.len();
+    }
+    Err(Error::Format("LWE public key ends before its digest.".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    fn test_key(seed: u64) -> ChaosKey {
+        ChaosKey { seed, hmac_key: BigUint::from_bytes_be(&[0xa5; 32]), bits: 256 }
+    }
+
+    #[test]
+    fn test_ring_arithmetic() {
+        let monomial = |degree: usize, coefficient: u16| -> Poly { std::array::from_fn(|i| if i == degree { coefficient } else { 0 }) };
+
+        // x * x^255 = x^256 = -1, and x^2 * 3x^3 + 5x * x^4 = 8x^5
+        assert_eq!(inner(&[monomial(1, 1)], &[monomial(255, 1)]), monomial(0, MODULUS - 1));
+        assert_eq!(inner(&[monomial(2, 1), monomial(1, 5)], &[monomial(3, 3), monomial(4, 1)]), monomial(5, 8));
+        assert_eq!(sub(&add(&monomial(7, MODULUS - 1), &monomial(7, 2)), &monomial(7, 3)), monomial(7, MODULUS - 2));
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let pair = LweKeyPair::from_chaos_key(&test_key(1));
+        assert_eq!(LweKeyPair::from_chaos_key(&test_key(1)).public, pair.public);
+        assert_ne!(LweKeyPair::from_chaos_key(&test_key(2)).public, pair.public);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(24);
+        for _ in 0..8 {
+            let message: [u8; MESSAGE_LENGTH] = rng.gen();
+            let ciphertext = pair.public.encrypt(&message, &mut rng);
+            assert_eq!(pair.secret.decrypt(&ciphertext), message);
+            let parsed = LweCiphertext::from_bytes(&ciphertext.to_bytes()).unwrap();
+            assert_eq!(parsed, ciphertext);
+        }
+        let coins = [7u8; SEED_LENGTH];
+        assert_eq!(pair.public.encrypt_with_coins(&[1; MESSAGE_LENGTH], &coins), pair.public.encrypt_with_coins(&[1; MESSAGE_LENGTH], &coins));
+
+        // Another key pair cannot decrypt
+        let other = LweKeyPair::from_chaos_key(&test_key(2));
+        assert_ne!(other.secret.decrypt(&pair.public.encrypt_with_coins(&[0x5a; MESSAGE_LENGTH], &coins)), [0x5a; MESSAGE_LENGTH]);
+    }
+
+    #[test]
+    fn test_kem_implicit_rejection() {
+        let pair = LweKeyPair::from_chaos_key(&test_key(4));
+        let mut rng = ChaCha20Rng::seed_from_u64(25);
+        let (shared_secret, encapsulation) = pair.public.encapsulate(&mut rng);
+        assert_eq!(pair.decapsulate(&encapsulation), shared_secret);
+        assert_ne!(pair.public.encapsulate(&mut rng).0, shared_secret);
+
+        // A modified ciphertext decapsulates to an unrelated, but repeatable, secret
+        let mut bytes = encapsulation.to_bytes();
+        bytes[0] ^= 1;
+        let tampered = LweCiphertext::from_bytes(&bytes).unwrap();
+        assert_ne!(pair.decapsulate(&tampered), shared_secret);
+        assert_eq!(pair.decapsulate(&tampered), pair.decapsulate(&tampered));
+        assert_ne!(LweKeyPair::from_chaos_key(&test_key(5)).decapsulate(&encapsulation), shared_secret);
+    }
+
+    #[test]
+    fn test_public_key_armor() {
+        let public = LweKeyPair::from_chaos_key(&test_key(3)).public;
+        let encoded = encode_public_key(&public);
+        assert_eq!(parse_public_key(&encoded).unwrap(), public);
+        assert_eq!(public.to_bytes().len(), PUBLIC_KEY_LENGTH);
+
+        // Change one hex digit inside the t record
+        let position = encoded.find('\n').unwrap() + 200;
+        let digit = if &encoded[position..position + 1] == "0" { "1" } else { "0" };
+        let tampered = format!("{}{}{}", &encoded[..position], digit, &encoded[position + 1..]);
+        assert!(parse_public_key(&tampered).is_err());
+
+        let mut bytes = public.to_bytes();
+        bytes[SEED_LENGTH..SEED_LENGTH + 2].copy_from_slice(&[0xff, 0x0f]);
+        assert!(LwePublicKey::from_bytes(&bytes).is_err());
+        assert!(LweCiphertext::from_bytes(&[0; CIPHERTEXT_LENGTH - 1]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Message authentication with HMAC (RFC 2104, FIPS 198-1) over SHA3 and
+//! KMAC (NIST SP 800-185).
+//!
+//! Every authenticated format in this crate goes through `Mac`, and tags are
+//! always verified in constant time.
+
+use hmac::digest::{ExtendableOutput, Update, XofReader};
+use hmac::{Hmac, Mac as _};
+use sha3::{CShake128, CShake128Core, CShake256, CShake256Core, Sha3_256, Sha3_512};
+use subtle::ConstantTimeEq;
+
+use crate::error::{Error, Result};
+
+/// Supported MAC constructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacAlgorithm {
+    /// HMAC with SHA3-256, 32-byte tags
+    HmacSha3_256,
+    /// HMAC with SHA3-512, 64-byte tags
+    HmacSha3_512,
+    /// KMAC128, 32-byte tags
+    Kmac128,
+    /// KMAC256, 64-byte tags
+    Kmac256,
+}
+
+impl MacAlgorithm {
+    /// Length of the tags produced by this algorithm in bytes
+    pub fn tag_length(&self) -> usize {
+        match self {
+            MacAlgorithm::HmacSha3_256 | MacAlgorithm::Kmac128 => 32,
+            MacAlgorithm::HmacSha3_512 | MacAlgorithm::Kmac256 => 64,
+        }
+    }
+
+    /// Stable identifier used in serialized formats
+    pub fn id(&self) -> u8 {
+        match self {
+            MacAlgorithm::HmacSha3_256 => 1,
+            MacAlgorithm::HmacSha3_512 => 2,
+            MacAlgorithm::Kmac128 => 3,
+            MacAlgorithm::Kmac256 => 4,
+        }
+    }
+
+    /// Look up an algorithm by its serialized identifier
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(MacAlgorithm::HmacSha3_256),
+            2 => Ok(MacAlgorithm::HmacSha3_512),
+            3 => Ok(MacAlgorithm::Kmac128),
+            4 => Ok(MacAlgorithm::Kmac256),
+            _ => Err(Error::Format(format!("Unknown MAC algorithm {}.", id))),
+        }
+    }
+}
+
+/// A keyed MAC instance
+#[derive(Clone)]
+pub struct Mac {
+    algorithm: MacAlgorithm,
+    key: Vec<u8>,
+    customization: Vec<u8>,
+}
+
+impl Mac {
+    /// Create a MAC with the given algorithm and key
+    pub fn new(algorithm: MacAlgorithm, key: &[u8]) -> Self {
+        Self {
+            algorithm,
+            key: key.to_vec(),
+            customization: Vec::new(),
+        }
+    }
+
+    /// Set the KMAC customization string `S` (ignored by HMAC)
+    pub fn with_customization(mut self, customization: &[u8]) -> Self {
+        self.customization = customization.to_vec();
+        self
+    }
+
+    /// The algorithm this MAC uses
+    pub fn algorithm(&self) -> MacAlgorithm {
+        self.algorithm
+    }
+
+    /// Compute the tag of `data`
+    pub fn compute(&self, data: &[u8]) -> Vec<u8> {
+        self.compute_parts(&[data])
+    }
+
+    /// Compute the tag of the concatenation of `parts`
+    pub fn compute_parts(&self, parts: &[&[u8]]) -> Vec<u8> {
+        match self.algorithm {
+            MacAlgorithm::HmacSha3_256 => {
+                let mut mac = Hmac::<Sha3_256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+                parts.iter().for_each(|part| hmac::Mac::update(&mut mac, part));
+                mac.finalize().into_bytes().to_vec()
+            }
+            MacAlgorithm::HmacSha3_512 => {
+                let mut mac = Hmac::<Sha3_512>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+                parts.iter().for_each(|part| hmac::Mac::update(&mut mac, part));
+                mac.finalize().into_bytes().to_vec()
+            }
+            MacAlgorithm::Kmac128 => {
+                let core = CShake128Core::new_with_function_name(b"KMAC", &self.customization);
+                kmac(CShake128::from_core(core), 168, &self.key, parts, 32)
+            }
+            MacAlgorithm::Kmac256 => {
+                let core = CShake256Core::new_with_function_name(b"KMAC", &self.customization);
+                kmac(CShake256::from_core(core), 136, &self.key, parts, 64)
+            }
+        }
+    }
+
+    /// Verify `tag` over `data` in constant time
+    pub fn verify(&self, data: &[u8], tag: &[u8]) -> Result<()> {
+        self.verify_parts(&[data], tag)
+    }
+
+    /// Verify `tag` over the concatenation of `parts` in constant time
+    pub fn verify_parts(&self, parts: &[&[u8]], tag: &[u8]) -> Result<()> {
+        if constant_time_eq(&self.compute_parts(parts), tag) {
+            Ok(())
+        } else {
+            Err(Error::Authentication)
+        }
+    }
+}
+
+/// Compare two byte strings without short-circuiting on the first difference
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Tag computed as `SHA3-256(key || data)` by files from older releases
+pub(crate) fn legacy_tag(key: &[u8], data: &[u8]) -> Vec<u8> {
+    <Sha3_256 as sha3::Digest>::digest([key, data].concat()).to_vec()
+}
+
+/// KMAC (SP 800-185 section 4.3) over a cSHAKE instance already carrying N = "KMAC" and S
+fn kmac<X: Update + ExtendableOutput>(mut xof: X, rate: usize, key: &[u8], parts: &[&[u8]], tag_length: usize) -> Vec<u8> {
+    let mut encoded_key = left_encode(key.len() as u64 * 8);
+    encoded_key.extend_from_slice(key);
+    xof.update(&bytepad(&encoded_key, rate));
+    parts.iter().for_each(|part| xof.update(part));
+    xof.update(&right_encode(tag_length as u64 * 8));
+
+    let mut tag = vec![0u8; tag_length];
+    xof.finalize_xof().read(&mut tag);
+    tag
+}
+
+/// Minimal big-endian encoding of `x`, at least one byte
+fn integer_bytes(x: u64) -> Vec<u8> {
+    let bytes = x.to_be_bytes();
+    let skip = bytes.iter().take(7).take_while(|&&b| b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+/// `left_encode` from SP 800-185 section 2.3.1
+fn left_encode(x: u64) -> Vec<u8> {
+    let bytes = integer_bytes(x);
+    let mut encoded = vec![bytes.len() as u8];
+    encoded.extend_from_slice(&bytes);
+    encoded
+}
+
+/// `right_encode` from SP 800-185 section 2.3.1
+fn right_encode(x: u64) -> Vec<u8> {
+    let mut encoded = integer_bytes(x);
+    encoded.push(encoded.len() as u8);
+    encoded
+}
+
+/// `bytepad` from SP 800-185 section 2.3.3
+fn bytepad(x: &[u8], w: usize) -> Vec<u8> {
+    let mut padded = left_encode(w as u64);
+    padded.extend_from_slice(x);
+    while !padded.len().is_multiple_of(w) {
+        padded.push(0);
+    }
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FIPS 198-1 / NIST CSRC HMAC-SHA3 example values, sample #1
+    #[test]
+    fn test_hmac_sha3_vectors() {
+        let message = b"Sample message for keylen<blocklen";
+
+        let key: Vec<u8> = (0u8..32).collect();
+        let tag = Mac::new(MacAlgorithm::HmacSha3_256, &key).compute(message);
+        assert_eq!(hex::encode(tag), "4fe8e202c4f058e8dddc23d8c34e467343e23555e24fc2f025d598f558f67205");
+
+        let key: Vec<u8> = (0u8..64).collect();
+        let tag = Mac::new(MacAlgorithm::HmacSha3_512, &key).compute(message);
+        assert_eq!(
+            hex::encode(tag),
+            "4efd629d6c71bf86162658f29943b1c308ce27cdfa6db0d9c3ce81763f9cbce5f7ebe9868031db1a8f8eb7b6b95e5c5e3f657a8996c86a2f6527e307f0213196"
+        );
+    }
+
+    // NIST SP 800-185 KMAC example values, samples #1, #2 and #4
+    #[test]
+    fn test_kmac_vectors() {
+        let key: Vec<u8> = (0x40u8..0x60).collect();
+        let data = [0x00, 0x01, 0x02, 0x03];
+
+        let tag = Mac::new(MacAlgorithm::Kmac128, &key).compute(&data);
+        assert_eq!(hex::encode(tag), "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e");
+
+        let mac = Mac::new(MacAlgorithm::Kmac128, &key).with_customization(b"My Tagged Application");
+        assert_eq!(
+            hex::encode(mac.compute(&data)),
+            "3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5"
+        );
+
+        let mac = Mac::new(MacAlgorithm::Kmac256, &key).with_customization(b"My Tagged Application");
+        assert_eq!(
+            hex::encode(mac.compute(&data)),
+            "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"
+        );
+    }
+
+    #[test]
+    fn test_verify_parts() {
+        for algorithm in [MacAlgorithm::HmacSha3_256, MacAlgorithm::HmacSha3_512, MacAlgorithm::Kmac128, MacAlgorithm::Kmac256] {
+            let mac = Mac::new(algorithm, b"key");
+            let tag = mac.compute(b"header and body");
+            assert_eq!(tag.len(), algorithm.tag_length());
+            assert_eq!(MacAlgorithm::from_id(algorithm.id()).unwrap(), algorithm);
+            assert!(mac.verify_parts(&[b"header", b" and body"], &tag).is_ok());
+            assert!(matches!(mac.verify(b"header and bodY", &tag), Err(Error::Authentication)));
+            assert!(matches!(mac.verify(b"header and body", &tag[1..]), Err(Error::Authentication)));
+        }
+    }
+}
@@ -1,473 +1,289 @@
-use sha3::{Digest, Sha3_256, Sha3_512};
-use num_bigint::{BigUint, RandBigInt};
-use num_traits::{Zero, One};
+use montgomery_chaos_lattices::basis::LatticeBasis;
+use montgomery_chaos_lattices::bkz::BkzParams;
+use montgomery_chaos_lattices::chaos::SequenceAlgorithm;
+use montgomery_chaos_lattices::chaos_analysis::{bifurcation, default_range, divergence, lyapunov_sweep, seed_sensitivity, to_csv};
+use montgomery_chaos_lattices::container::{CipherSuite, Container, KdfId};
+use montgomery_chaos_lattices::estimator::{estimate, DEFAULT_BLOCK_SIZES};
+use montgomery_chaos_lattices::hybrid;
+use montgomery_chaos_lattices::hyper_prime::small_prime_sieve;
+use montgomery_chaos_lattices::key::{decode_chaos_key, encode_chaos_key, encrypt_chaos_key, save_chaos_key, ChaosKey};
+use montgomery_chaos_lattices::lattice::{KeySchedule, Lattice};
+use montgomery_chaos_lattices::lwe::{decode_public_key, encode_public_key, LweKeyPair};
+use montgomery_chaos_lattices::mac::MacAlgorithm;
+use montgomery_chaos_lattices::nonce::{NonceGenerator, NONCE_LENGTH};
+use montgomery_chaos_lattices::passphrase::PassphraseParams;
+use montgomery_chaos_lattices::sbox_analysis::SboxThresholds;
+use montgomery_chaos_lattices::stats::{analyze_chaotic_sequence, analyze_keystream};
+use montgomery_chaos_lattices::stream::{DecryptReader, EncryptWriter, StreamHeader};
 use rand::rngs::OsRng;
 use std::env;
-use std::fs::{read_to_string, write};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::process;
-use hex;
-
-/// Generate a list of small primes up to a given limit using the Sieve of Eratosthenes
-fn small_prime_sieve(limit: usize) -> Vec<u64> {
-    let mut sieve = vec![true; limit + 1];
-    let mut p = 2;
-    while p * p <= limit {
-        if sieve[p] {
-            let mut multiple = p * p;
-            while multiple <= limit {
-                sieve[multiple] = false;
-                multiple += p;
-            }
-        }
-        p += 1;
-    }
-    let mut primes = Vec::new();
-    for i in 2..=limit {
-        if sieve[i] {
-            primes.push(i as u64);
-        }
-    }
-    primes
-}
 
-/// Quick check using small primes to eliminate trivial composites
-#[inline(always)]
-fn passes_small_prime_check(n: &BigUint, small_primes: &[u64]) -> bool {
-    for &prime in small_primes {
-        let prime_big = BigUint::from(prime);
-        if n % &prime_big == BigUint::zero() {
-            return *n == prime_big;
-        }
+/// Read a whole file as raw bytes, or stdin when `path` is `-`
+fn read_input(path: &str) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut buffer = Vec::new();
+        io::stdin().lock().read_to_end(&mut buffer)?;
+        Ok(buffer)
+    } else {
+        fs::read(path)
     }
-    true
 }
 
-/// Miller-Rabin primality test with a chosen number of rounds.
-/// This function assumes n has passed small prime checks.
-#[inline(always)]
-fn miller_rabin(rng: &mut OsRng, n: &BigUint, k: usize) -> bool {
-    if *n <= BigUint::one() {
-        return false;
-    }
-    if *n == BigUint::from(2u64) {
-        return true;
-    }
-    if n % 2u64 == BigUint::zero() {
-        return false;
-    }
-
-    let one = BigUint::one();
-    let two = &one + &one;
-    let n_minus_one = n - &one;
-    let mut d = n_minus_one.clone();
-    let mut s = 0;
-    while &d % &two == BigUint::zero() {
-        d >>= 1;
-        s += 1;
+/// Write raw bytes to a file, or stdout when `path` is `-`
+fn write_output(path: &str, data: &[u8]) -> io::Result<()> {
+    if path == "-" {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(data)?;
+        stdout.flush()
+    } else {
+        fs::write(path, data)
     }
+}
 
-    'outer: for _ in 0..k {
-        let a = rng.gen_biguint_range(&two, n);
-        let mut x = a.modpow(&d, n);
-        if x == one || x == n_minus_one {
-            continue;
-        }
-        for _ in 0..(s - 1) {
-            x = x.modpow(&two, n);
-            if x == n_minus_one {
-                continue 'outer;
-            }
-        }
-        return false;
+/// Open a file for buffered streaming reads, or stdin when `path` is `-`
+fn open_input(path: &str) -> io::Result<Box<dyn Read>> {
+    if path == "-" {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
     }
-    true
 }
 
-/// Combined check: first small primes, then Miller-Rabin
-#[inline(always)]
-fn is_probably_prime(rng: &mut OsRng, n: &BigUint, small_primes: &[u64], rounds: usize) -> bool {
-    if !passes_small_prime_check(n, small_primes) {
-        return false;
+/// Create a file for buffered streaming writes, or stdout when `path` is `-`
+fn create_output(path: &str) -> io::Result<Box<dyn Write>> {
+    if path == "-" {
+        Ok(Box::new(io::stdout().lock()))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
     }
-    miller_rabin(rng, n, rounds)
 }
 
-/// Generate a large "hyper" prime by quickly filtering out composites with small primes,
-/// then using Miller-Rabin for final checks. This uses OsRng for cryptographically
-/// secure random bytes when constructing the candidate.
-#[inline(always)]
-fn generate_hyper_prime(rng: &mut OsRng, bits: usize, small_primes: &[u64], rounds: usize) -> BigUint {
-    loop {
-        let candidate = rng.gen_biguint(bits.try_into().unwrap());
-        if is_probably_prime(rng, &candidate, small_primes, rounds) {
-            return candidate;
-        }
+/// Print "<message> <output>" without mixing it into piped output
+fn report(output: &str, message: &str) {
+    if output == "-" {
+        eprintln!("{} stdout", message);
+    } else {
+        println!("{} {}", message, output);
     }
 }
 
-/// Structure for a lattice point
-#[derive(Debug, Clone)]
-struct LatticePoint {
-    coordinates: Vec<BigUint>, // Coordinates in the lattice
+/// Remove `flag` from the arguments, returning whether it was present
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != before
 }
 
-/// Structure for the lattice
-#[derive(Debug)]
-struct Lattice {
-    points: Vec<LatticePoint>,    // Lattice points
-    dimensions: usize,            // Number of dimensions
-    prime_anchors: Vec<BigUint>,  // Prime anchors derived from rows
-    sbox: [u8; 256],              // Substitution box
-    inverse_sbox: [u8; 256],      // Inverse substitution box
+/// Remove `option` and its value from the arguments, returning the value
+fn take_option(args: &mut Vec<String>, option: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == option)?;
+    if index + 1 >= args.len() {
+        eprintln!("{} requires a value.", option);
+        process::exit(1);
+    }
+    args.remove(index);
+    Some(args.remove(index))
 }
 
-impl Lattice {
-    /// Create the genesis lattice
-    fn new(dimensions: usize, size: usize, prime_bits: usize, small_primes: &[u64], rounds: usize) -> Self {
-        let mut points = Vec::new();
-        let mut rng = OsRng;
-
-        for _ in 0..size {
-            let mut coordinates = Vec::new();
-            for _ in 0..dimensions {
-                let prime = generate_hyper_prime(&mut rng, prime_bits, small_primes, rounds);
-                coordinates.push(prime);
-            }
-            points.push(LatticePoint { coordinates });
-        }
-
-        Self {
-            points,
-            dimensions,
-            prime_anchors: Vec::new(),
-            sbox: [0u8; 256],
-            inverse_sbox: [0u8; 256],
+/// Parse the value given for `option`, exiting on malformed input
+fn parse_value<T: std::str::FromStr>(option: &str, value: &str) -> T {
+    match value.parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("Invalid value for {}: {}", option, value);
+            process::exit(1);
         }
     }
+}
 
-    /// Get coordinates of all lattice points
-    fn get_coordinates(&self) -> Vec<Vec<BigUint>> {
-        self.points
-            .iter()
-            .map(|point| point.coordinates.clone())
-            .collect()
-    }
+/// Remove `option` and parse its value, falling back to `default` when absent
+fn take_parsed_option<T: std::str::FromStr>(args: &mut Vec<String>, option: &str, default: T) -> T {
+    take_option(args, option).map_or(default, |value| parse_value(option, &value))
+}
 
-    /// Bind lattice rows using Montgomery ladder and chaos transformations
-    fn bind_with_chaos(&mut self, scalar: &BigUint, chaos_seq: &[usize]) {
-        for point in &mut self.points {
-            let mut r0 = point.coordinates.clone();
-            let mut r1 = point.coordinates.clone();
-
-            for (bit, chaos_val) in scalar
-                .to_bytes_be()
-                .iter()
-                .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1))
-                .zip(chaos_seq)
-            {
-                if bit == 0 {
-                    for i in 0..self.dimensions {
-                        r1[i] = r0[i].clone() + &r1[i] + BigUint::from(*chaos_val as u64);
-                        r0[i] = r0[i].clone() * BigUint::from(2u32);
-                    }
-                } else {
-                    for i in 0..self.dimensions {
-                        r0[i] = r0[i].clone() + &r1[i] + BigUint::from(*chaos_val as u64);
-                        r1[i] = r1[i].clone() * BigUint::from(2u32);
-                    }
-                }
-                Self::orthogonalize(&mut r0, &mut r1);
-            }
-            point.coordinates = r0;
-        }
+/// Remove the S-Box threshold options, falling back to the default thresholds
+fn take_sbox_thresholds(args: &mut Vec<String>) -> SboxThresholds {
+    let defaults = SboxThresholds::default();
+    SboxThresholds {
+        min_nonlinearity: take_parsed_option(args, "--min-nonlinearity", defaults.min_nonlinearity),
+        max_differential_uniformity: take_parsed_option(args, "--max-differential-uniformity", defaults.max_differential_uniformity),
+        max_sac_deviation: take_parsed_option(args, "--max-sac-deviation", defaults.max_sac_deviation),
+        max_bic_correlation: take_parsed_option(args, "--max-bic-correlation", defaults.max_bic_correlation),
+        max_fixed_points: take_parsed_option(args, "--max-fixed-points", defaults.max_fixed_points),
     }
+}
 
-    /// Ensure orthogonality between two vectors
-    fn orthogonalize(v1: &mut Vec<BigUint>, v2: &mut Vec<BigUint>) {
-        let mut dot_product = BigUint::zero();
-        let mut magnitude_squared = BigUint::zero();
+/// Environment variable holding the key passphrase
+const PASSPHRASE_ENV: &str = "CHAOS_KEY_PASSPHRASE";
 
-        for (x1, x2) in v1.iter().zip(v2.iter()) {
-            dot_product = dot_product + (x1 * x2);
-            magnitude_squared = magnitude_squared + (x2 * x2);
-        }
+/// Default number of bytes tested by `stats`
+const STATS_LENGTH: usize = 125_000;
 
-        if magnitude_squared.is_zero() {
-            return;
-        }
+/// Number of 256-element permutations in the cycle structure test
+const STATS_CYCLE_TRIALS: u64 = 1000;
 
-        let projection_scalar = &dot_product / &magnitude_squared;
-        for (x1, x2) in v1.iter_mut().zip(v2.iter()) {
-            *x1 = x1.clone() - &(x2 * &projection_scalar);
-        }
-    }
+/// Parameter values in a `chaos-analyze` sweep
+const ANALYZE_STEPS: usize = 200;
 
-    /// Generate S-Box and inverse S-Box using the chaos seed
-    fn generate_sbox(&mut self, chaos_seed: u64) {
-        let mut hasher = Sha3_256::new();
-        hasher.update(&chaos_seed.to_be_bytes());
-        for anchor in &self.prime_anchors {
-            hasher.update(&anchor.to_bytes_be());
-        }
-        let seed = hasher.finalize();
+/// Iterations per Lyapunov estimate
+const ANALYZE_ITERATIONS: usize = 10_000;
 
-        let mut sbox = [0u8; 256];
-        for i in 0..256 {
-            sbox[i] = i as u8;
-        }
+/// Orbit points recorded per parameter of a bifurcation diagram
+const ANALYZE_SAMPLES: usize = 100;
 
-        let chaos_seed_u64 = u64::from_be_bytes(seed[0..8].try_into().unwrap());
-        let chaos_seq = chaotic_sequence(256, chaos_seed_u64);
-        for i in 0..256 {
-            sbox.swap(i, chaos_seq[i % chaos_seq.len()]);
-        }
+/// Trial seeds per flipped bit in the sensitivity analysis
+const ANALYZE_TRIALS: u64 = 32;
 
-        for (i, &byte) in sbox.iter().enumerate() {
-            self.inverse_sbox[byte as usize] = i as u8;
-        }
+/// Rank and dimension of the square lattice `lattice-audit` builds by default.
+/// The cipher's own 3×256 lattice is too small to price.
+const AUDIT_DIMENSIONS: usize = 16;
 
-        self.sbox = sbox;
-    }
-
-    /// Encrypt a message using the S-Box and chaotic sequence
-    fn encrypt(&self, plaintext: &[u8], chaos_seed: u64) -> Vec<u8> {
-        let chaos_seq = chaotic_sequence(plaintext.len(), chaos_seed);
-        plaintext
-            .iter()
-            .enumerate()
-            .map(|(i, &byte)| self.sbox[(byte ^ chaos_seq[i] as u8) as usize])
-            .collect()
-    }
-
-    /// Decrypt a message using the inverse S-Box and chaotic sequence
-    fn decrypt(&self, ciphertext: &[u8], chaos_seed: u64) -> Vec<u8> {
-        let chaos_seq = chaotic_sequence(ciphertext.len(), chaos_seed);
-        ciphertext
-            .iter()
-            .enumerate()
-            .map(|(i, &byte)| {
-                let index = self.inverse_sbox[byte as usize];
-                index ^ chaos_seq[i] as u8
-            })
-            .collect()
+/// Read the key passphrase from `--passphrase-file`, falling back to the environment
+fn read_passphrase(passphrase_file: Option<String>) -> Option<Vec<u8>> {
+    match passphrase_file {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(content) => Some(content.trim_end_matches(['\r', '\n']).as_bytes().to_vec()),
+            Err(e) => {
+                eprintln!("Failed to read passphrase file: {}", e);
+                process::exit(1);
+            }
+        },
+        None => env::var(PASSPHRASE_ENV).ok().map(String::into_bytes),
     }
 }
 
-/// Chaos utility functions
-
-fn enhanced_perturbation(state: u64, step: u64) -> u64 {
-    let input = format!("{}-{}", state, step);
-    let hash = Sha3_256::digest(input.as_bytes());
-    u64::from_be_bytes(hash[0..8].try_into().unwrap())
-}
-
-fn chaotic_sequence(n: usize, seed: u64) -> Vec<usize> {
-    let mut chaos_seq: Vec<usize> = (0..n).collect();
-    let mut current_state = seed;
-
-    for i in 0..n {
-        let perturbation = enhanced_perturbation(current_state, i as u64);
-        let mod_pi = (current_state % ((std::f64::consts::PI * 1e8) as u64)) as f64 / 1e8;
-        let trig_transform = (mod_pi.sin() * mod_pi.cos()).abs();
-        let chaotic_index = ((trig_transform * n as f64) as u64 + perturbation) as usize % n;
-
-        chaos_seq.swap(i, chaotic_index);
-        current_state = current_state.wrapping_add(perturbation) % n as u64;
+/// Encode a key for saving, protecting it when a passphrase is set
+fn encode_key(key: &ChaosKey, passphrase: Option<&[u8]>) -> montgomery_chaos_lattices::Result<String> {
+    match passphrase {
+        Some(passphrase) => encrypt_chaos_key(key, passphrase, &PassphraseParams::default()),
+        None => Ok(encode_chaos_key(key)),
     }
-
-    chaos_seq
 }
 
-/// HMAC Utility Functions
-
-/// Generate an HMAC using SHA3-256
-fn generate_hmac_sha3(data: &str, key: &[u8]) -> String {
-    let mut hasher = Sha3_256::new();
-    hasher.update(key);
-    hasher.update(data.as_bytes());
-    hasher
-        .finalize()
-        .iter()
-        .map(|byte| format!("{:02x}", byte))
-        .collect()
-}
+/// Parse and validate the `<bits>` argument
+fn parse_bits(arg: &str) -> usize {
+    let bits: usize = match arg.parse() {
+        Ok(num) => num,
+        Err(_) => {
+            eprintln!("Invalid bits argument. Must be a positive integer.");
+            process::exit(1);
+        }
+    };
 
-/// Verify HMAC using SHA3-256
-fn verify_hmac_sha3(data_hex: &str, hmac: &str, key: &[u8]) -> bool {
-    let recalculated_hmac = generate_hmac_sha3(data_hex, key);
-    recalculated_hmac == hmac
-}
+    // Validate that bits is divisible by 64 and >= 64
+    if bits < 64 || !bits.is_multiple_of(64) {
+        eprintln!("Bits must be a multiple of 64 and at least 64.");
+        process::exit(1);
+    }
 
-/// Wrap the key or ciphertext in PEM-like format with line breaks every 64 characters
-fn wrap_in_pem_format(label: &str, key: &str) -> String {
-    let wrapped_key: String = key
-        .as_bytes()
-        .chunks(64)
-        .map(|chunk| std::str::from_utf8(chunk).unwrap())
-        .collect::<Vec<&str>>()
-        .join("\n");
-
-    format!(
-        "--- BEGIN {} ---\n{}\n--- END {} ---",
-        label, wrapped_key, label
-    )
+    bits
 }
 
-/// Generate the chaos key
-fn generate_chaos_key(bits: usize, small_prime_limit: usize) -> String {
-    // Generate the nonce (seed)
-    let nonce = generate_nonce(bits, small_prime_limit);
-
-    // Generate the HMAC key prime
-    let hmac_key_prime = generate_hyper_prime(&mut OsRng, bits.try_into().unwrap(), &small_prime_sieve(small_prime_limit), 40);
-
-    // Generate the chaotic sequence using the nonce
-    let chaos_seq = chaotic_sequence(256, nonce);
-
-    // Serialize the chaotic sequence to a hex string
-    let data_string: String = chaos_seq.iter().map(|&num| format!("{:02x}", num)).collect();
-
-    // Generate the HMAC value using the HMAC key
-    let hmac_value = generate_hmac_sha3(&data_string, &hmac_key_prime.to_bytes_be());
-
-    // Encode nonce and hmac_key as hex
-    let nonce_hex = hex::encode(nonce.to_be_bytes());
-    let hmac_key_hex = hex::encode(hmac_key_prime.to_bytes_be());
-
-    // Concatenate nonce_hex + hmac_key_hex + data_string + hmac_value
-    let encoded_data = format!("{}{}{}{}", nonce_hex, hmac_key_hex, data_string, hmac_value);
-
-    // Wrap the key in PEM-like format
-    wrap_in_pem_format("CHAOS KEY", &encoded_data)
+/// Remove a `<bits>` argument left over from older invocations, if present.
+///
+/// Key files now record their own size, so the argument is only checked.
+fn take_legacy_bits(args: &mut Vec<String>, expected_len: usize) -> Option<usize> {
+    if args.len() == expected_len + 1 {
+        Some(parse_bits(&args.remove(2)))
+    } else {
+        None
+    }
 }
 
-/// Generate a 64-bit nonce (seed)
-fn generate_nonce(bits: usize, small_prime_limit: usize) -> u64 {
-    let small_primes = small_prime_sieve(small_prime_limit);
-    let mut rng = OsRng;
-    let rounds = 40;
-    let prime_bits = bits;
-    let chaos_seed_big = generate_hyper_prime(&mut rng, prime_bits.try_into().unwrap(), &small_primes, rounds);
-    let mut hasher = Sha3_512::new();
-    hasher.update(chaos_seed_big.to_bytes_be());
-    let chaos_seed_bytes = hasher.finalize();
-    u64::from_be_bytes(chaos_seed_bytes[0..8].try_into().unwrap())
+/// Decode the chaos key, returning the chaos seed and HMAC key bytes
+fn load_chaos_key(key_file: &str, bits: Option<usize>, passphrase: Option<&[u8]>) -> (u64, Vec<u8>) {
+    match decode_chaos_key(key_file, passphrase) {
+        Ok(key) => {
+            if let Some(bits) = bits.filter(|&bits| bits != key.bits) {
+                eprintln!("Failed to decode chaos key: {} is a {}-bit key, not {}-bit.", key_file, key.bits, bits);
+                process::exit(1);
+            }
+            (key.seed, key.hmac_key_bytes())
+        }
+        Err(e) => {
+            eprintln!("Failed to decode chaos key: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-/// Save a chaos key to a file
-fn save_chaos_key(filename: &str, key: &str) -> std::io::Result<()> {
-    write(filename, key)
+/// Derive the lattice and its S-Box from the chaos key
+fn build_lattice(seed: u64, hmac_key: &[u8], schedule: KeySchedule) -> Lattice {
+    build_lattice_with_shape(seed, hmac_key, schedule, 256, 3)
 }
 
-/// Decode a chaos key from PEM-like format
-fn decode_chaos_key(bits: usize, filename: &str) -> Result<(u64, BigUint, String), String> {
-    let content = read_to_string(filename).map_err(|e| e.to_string())?;
-    if !content.starts_with("--- BEGIN CHAOS KEY ---") || !content.ends_with("--- END CHAOS KEY ---") {
-        return Err("Invalid Chaos Key format.".to_string());
-    }
-
-    // Extract the encoded data between the markers
-    let encoded_data = content
-        .lines()
-        .skip(1)
-        .take_while(|line| *line != "--- END CHAOS KEY ---")
-        .collect::<Vec<&str>>()
-        .join("");
-
-    // Calculate seed (nonce), hmac_key, data, and hmac_value lengths in hex
-    let seed_hex_length = 16; // 64-bit nonce = 16 hex characters
-    let hmac_key_bits = bits;
-    let hmac_key_bytes = hmac_key_bits / 8;
-    let hmac_key_hex_length = hmac_key_bytes * 2;
-    let data_hex_length = 512; // 256-byte chaos sequence = 512 hex characters
-    let hmac_value_hex_length = 64; // SHA3-256 = 256 bits = 64 hex characters
-
-    let expected_length = seed_hex_length + hmac_key_hex_length + data_hex_length + hmac_value_hex_length;
-
-    if encoded_data.len() != expected_length {
-        return Err(format!(
-            "Encoded data length mismatch. Expected {}, found {}.",
-            expected_length,
-            encoded_data.len()
-        ));
-    }
-
-    // Extract seed_hex, hmac_key_hex, data_hex, hmac_value
-    let seed_hex = &encoded_data[0..seed_hex_length];
-    let hmac_key_hex = &encoded_data[seed_hex_length..seed_hex_length + hmac_key_hex_length];
-    let data_hex = &encoded_data[seed_hex_length + hmac_key_hex_length..seed_hex_length + hmac_key_hex_length + data_hex_length];
-    let hmac_value = &encoded_data[seed_hex_length + hmac_key_hex_length + data_hex_length..];
-
-    // Convert hex to bytes
-    let seed_bytes = hex::decode(seed_hex).map_err(|_| "Invalid nonce hex encoding.".to_string())?;
-    let hmac_key_bytes_vec = hex::decode(hmac_key_hex).map_err(|_| "Invalid HMAC key hex encoding.".to_string())?;
-    let _data_bytes = hex::decode(data_hex).map_err(|_| "Invalid data hex encoding.".to_string())?;
+/// Like `build_lattice`, with `size` points of `dimensions` coordinates
+fn build_lattice_with_shape(seed: u64, hmac_key: &[u8], schedule: KeySchedule, dimensions: usize, size: usize) -> Lattice {
+    let prime_bits = 256;
+    let small_primes = small_prime_sieve(10_000);
+    let rounds = 40; // Increased for better security
 
-    // Convert seed bytes to u64
-    let seed_array: [u8; 8] = seed_bytes.try_into().map_err(|_| "Invalid nonce byte length.".to_string())?;
-    let seed = u64::from_be_bytes(seed_array);
-
-    // Convert hmac_key bytes to BigUint
-    let hmac_key_prime = BigUint::from_bytes_be(&hmac_key_bytes_vec);
-
-    // Generate the chaotic sequence using the seed (nonce)
-    let chaos_seq = chaotic_sequence(256, seed);
-
-    // Serialize the chaotic sequence to a hex string
-    let data_string: String = chaos_seq.iter().map(|&num| format!("{:02x}", num)).collect();
-
-    // Generate the HMAC value using the HMAC key
-    let recalculated_hmac = generate_hmac_sha3(&data_string, &hmac_key_prime.to_bytes_be());
-
-    // Verify HMAC
-    if recalculated_hmac != hmac_value {
-        return Err("HMAC verification failed. The data may have been tampered with.".to_string());
+    match Lattice::from_chaos_key_with_schedule(schedule, seed, hmac_key, dimensions, size, prime_bits, &small_primes, rounds) {
+        Ok(lattice) => lattice,
+        Err(e) => {
+            eprintln!("Failed to initialize lattice: {}", e);
+            process::exit(1);
+        }
     }
-
-    Ok((seed, hmac_key_prime, recalculated_hmac))
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let binary = take_flag(&mut args, "--binary");
+    let passphrase_file = take_option(&mut args, "--passphrase-file");
+    let passphrase = read_passphrase(passphrase_file);
+    let sbox_thresholds = take_sbox_thresholds(&mut args);
 
     if args.len() < 2 {
         eprintln!("Usage:");
         eprintln!("  {} <command> [arguments]", args[0]);
         eprintln!("Commands:");
         eprintln!("  gen <bits> <output_file>");
-        eprintln!("  verify <bits> <input_file>");
-        eprintln!("  encrypt <bits> <input_file> <plaintext_file> <ciphertext_file>");
-        eprintln!("  decrypt <bits> <input_file> <ciphertext_file> <decrypted_file>");
+        eprintln!("  keygen-pk [<bits>] <identity_file> <public_key_file>");
+        eprintln!("  verify <input_file>");
+        eprintln!("  migrate-key <input_file> <output_file>");
+        eprintln!("  encrypt <input_file> <plaintext_file> <ciphertext_file> [--binary]");
+        eprintln!("  encrypt --recipient <public_key_file> <plaintext_file> <ciphertext_file> [--binary]");
+        eprintln!("  decrypt <input_file> <ciphertext_file> <decrypted_file>");
+        eprintln!("  decrypt --identity <identity_file> <ciphertext_file> <decrypted_file>");
+        eprintln!("  encrypt-stream <input_file> <plaintext_file> <ciphertext_file>");
+        eprintln!("  decrypt-stream <input_file> <ciphertext_file> <decrypted_file>");
+        eprintln!("  stats <input_file> [--length <bytes>]");
+        eprintln!("  chaos-analyze <lyapunov|bifurcation|divergence|sensitivity> <map|all> [<csv_file>] [--from <p>] [--to <p>] [--steps <n>] [--length <n>] [--trials <n>]");
+        eprintln!("  lattice-audit <input_file> [--block-size <beta>] [--bkz] [--dimensions <n>] [--size <m>]");
+        eprintln!("Options:");
+        eprintln!("  --passphrase-file <file>  Passphrase protecting the key (or set {})", PASSPHRASE_ENV);
+        eprintln!("  --min-nonlinearity <n>, --max-differential-uniformity <n>, --max-sac-deviation <x>,");
+        eprintln!("  --max-bic-correlation <x>, --max-fixed-points <n>");
+        eprintln!("                            Limits the S-Box of every encryption nonce must meet; weaker nonces are redrawn");
+        eprintln!("Use - as a plaintext, ciphertext or decrypted file to read stdin or write stdout.");
         process::exit(1);
     }
 
-    let command = args[1].as_str();
+    let command = args[1].clone();
 
-    match command {
+    match command.as_str() {
         "gen" => {
             if args.len() != 4 {
                 eprintln!("Usage: {} gen <bits> <output_file>", args[0]);
                 process::exit(1);
             }
 
-            let bits: usize = match args[2].parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    eprintln!("Invalid bits argument. Must be a positive integer.");
-                    process::exit(1);
-                }
-            };
-
+            let bits = parse_bits(&args[2]);
             let output_file = &args[3];
 
-            // Validate that bits is divisible by 64 and >= 64
-            if bits < 64 || bits % 64 != 0 {
-                eprintln!("Bits must be a multiple of 64 and at least 64.");
-                process::exit(1);
-            }
-
             // Generate the chaos key
-            let chaos_key = generate_chaos_key(bits, 10_000);
+            let generated = ChaosKey::generate(bits, 10_000);
+            let chaos_key = match generated.and_then(|key| encode_key(&key, passphrase.as_deref())) {
+                Ok(key) => key,
+                Err(e) => {
+                    eprintln!("Failed to generate chaos key: {}", e);
+                    process::exit(1);
+                }
+            };
 
             // Save the chaos key to the file
             if let Err(e) = save_chaos_key(output_file, &chaos_key) {
@@ -477,32 +293,66 @@ fn main() {
 
             println!("Chaos key successfully saved to {}", output_file);
         }
-        "verify" => {
-            if args.len() != 4 {
-                eprintln!("Usage: {} verify <bits> <input_file>", args[0]);
+        "keygen-pk" => {
+            if args.len() != 4 && args.len() != 5 {
+                eprintln!("Usage: {} keygen-pk [<bits>] <identity_file> <public_key_file>", args[0]);
                 process::exit(1);
             }
 
-            let bits: usize = match args[2].parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    eprintln!("Invalid bits argument. Must be a positive integer.");
-                    process::exit(1);
+            let identity_file = &args[args.len() - 2];
+            let public_key_file = &args[args.len() - 1];
+
+            // With <bits>, generate a new identity; otherwise publish an existing chaos key
+            let key = if args.len() == 5 {
+                let bits = parse_bits(&args[2]);
+                let saved = ChaosKey::generate(bits, 10_000).and_then(|key| {
+                    save_chaos_key(identity_file, &encode_key(&key, passphrase.as_deref())?)?;
+                    Ok(key)
+                });
+                match saved {
+                    Ok(key) => key,
+                    Err(e) => {
+                        eprintln!("Failed to generate identity: {}", e);
+                        process::exit(1);
+                    }
+                }
+            } else {
+                match decode_chaos_key(identity_file, passphrase.as_deref()) {
+                    Ok(key) => key,
+                    Err(e) => {
+                        eprintln!("Failed to decode identity: {}", e);
+                        process::exit(1);
+                    }
                 }
             };
 
-            let input_file = &args[3];
-
-            // Validate that bits is divisible by 64 and >= 64
-            if bits < 64 || bits % 64 != 0 {
-                eprintln!("Bits must be a multiple of 64 and at least 64.");
+            let public_key = encode_public_key(&LweKeyPair::from_chaos_key(&key).public);
+            if let Err(e) = write_output(public_key_file, public_key.as_bytes()) {
+                eprintln!("Failed to save public key: {}", e);
                 process::exit(1);
             }
+            if args.len() == 5 {
+                println!("Identity successfully saved to {}", identity_file);
+            }
+            report(public_key_file, "Public key successfully saved to");
+        }
+        "verify" => {
+            let bits = take_legacy_bits(&mut args, 3);
+            if args.len() != 3 {
+                eprintln!("Usage: {} verify <input_file>", args[0]);
+                process::exit(1);
+            }
+
+            let input_file = &args[2];
 
             // Decode the chaos key
-            match decode_chaos_key(bits, input_file) {
-                Ok((_seed, _hmac_key_prime, _hmac_value)) => {
-                    println!("Chaos key verification successful. HMAC is valid.");
+            match decode_chaos_key(input_file, passphrase.as_deref()) {
+                Ok(key) if bits.is_some_and(|bits| bits != key.bits) => {
+                    eprintln!("Chaos key verification failed: {} is a {}-bit key.", input_file, key.bits);
+                    process::exit(1);
+                }
+                Ok(key) => {
+                    println!("Chaos key verification successful. HMAC is valid ({}-bit key).", key.bits);
                 }
                 Err(e) => {
                     eprintln!("Chaos key verification failed: {}", e);
@@ -510,191 +360,367 @@ fn main() {
                 }
             }
         }
-        "encrypt" => {
-            if args.len() != 6 {
-                eprintln!("Usage: {} encrypt <bits> <input_file> <plaintext_file> <ciphertext_file>", args[0]);
+        "migrate-key" => {
+            if args.len() != 4 {
+                eprintln!("Usage: {} migrate-key <input_file> <output_file>", args[0]);
                 process::exit(1);
             }
 
-            let bits: usize = match args[2].parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    eprintln!("Invalid bits argument. Must be a positive integer.");
-                    process::exit(1);
-                }
-            };
+            let input_file = &args[2];
+            let output_file = &args[3];
 
-            let input_file = &args[3];
-            let plaintext_file = &args[4];
-            let ciphertext_file = &args[5];
+            // Re-encode a key of any supported format in the current format
+            let result = decode_chaos_key(input_file, passphrase.as_deref())
+                .and_then(|key| encode_key(&key, passphrase.as_deref()))
+                .and_then(|encoded| save_chaos_key(output_file, &encoded));
+            if let Err(e) = result {
+                eprintln!("Failed to migrate chaos key: {}", e);
+                process::exit(1);
+            }
 
-            // Validate that bits is divisible by 64 and >= 64
-            if bits < 64 || bits % 64 != 0 {
-                eprintln!("Bits must be a multiple of 64 and at least 64.");
+            println!("Chaos key successfully migrated to {}", output_file);
+        }
+        "encrypt" => {
+            // A recipient's public key takes the place of the chaos key
+            let recipient_file = take_option(&mut args, "--recipient");
+            if let Some(recipient_file) = &recipient_file {
+                args.insert(2, recipient_file.clone());
+            }
+            let bits = take_legacy_bits(&mut args, 5);
+            if args.len() != 5 {
+                eprintln!("Usage: {} encrypt <input_file> <plaintext_file> <ciphertext_file> [--binary]", args[0]);
+                eprintln!("       {} encrypt --recipient <public_key_file> <plaintext_file> <ciphertext_file> [--binary]", args[0]);
                 process::exit(1);
             }
 
-            // Decode the chaos key
-            let (seed, hmac_key_prime, _hmac_value) = match decode_chaos_key(bits, input_file) {
-                Ok((s, k, v)) => (s, k, v),
+            let input_file = &args[2];
+            let plaintext_file = &args[3];
+            let ciphertext_file = &args[4];
+
+            let recipient = recipient_file.map(|_| match decode_public_key(input_file) {
+                Ok(public_key) => public_key,
                 Err(e) => {
-                    eprintln!("Failed to decode chaos key: {}", e);
+                    eprintln!("Failed to decode public key: {}", e);
                     process::exit(1);
                 }
-            };
+            });
+            let chaos_key = recipient.is_none().then(|| load_chaos_key(input_file, bits, passphrase.as_deref()));
 
             // Load plaintext
-            let plaintext = match read_to_string(plaintext_file) {
-                Ok(content) => content.into_bytes(),
+            let plaintext = match read_input(plaintext_file) {
+                Ok(content) => content,
                 Err(e) => {
                     eprintln!("Failed to read plaintext file: {}", e);
                     process::exit(1);
                 }
             };
 
-            // Initialize lattice using the chaos seed and HMAC key
-            let dimensions = 256;
-            let size = 3;
-            let prime_bits = 256;
-            let small_primes = small_prime_sieve(10_000);
-            let scalar = BigUint::from(2u64);
-            let rounds = 40; // Increased for better security
-
-            let mut lattice = Lattice::new(dimensions, size, prime_bits, &small_primes, rounds);
-
-            // Bind lattice with chaos
-            lattice.bind_with_chaos(&scalar, &chaotic_sequence(dimensions, seed));
-
-            // Generate S-Box
-            lattice.generate_sbox(seed);
-
-            // Encrypt the plaintext
-            let ciphertext = lattice.encrypt(&plaintext, seed);
-
-            // Generate HMAC over the ciphertext for AEAD
-            let ciphertext_hex = hex::encode(&ciphertext);
-            let hmac_value = generate_hmac_sha3(&ciphertext_hex, &hmac_key_prime.to_bytes_be());
-
-            // Encode ciphertext and HMAC in PEM-like format
-            let encoded_ciphertext = format!("{}{}", ciphertext_hex, hmac_value);
-            let wrapped_ciphertext = wrap_in_pem_format("CIPHERTEXT", &encoded_ciphertext);
+            // Encrypt the plaintext under a fresh nonce and seal both in an authenticated container
+            let mut nonces = NonceGenerator::random();
+            let sealed = match (&recipient, chaos_key) {
+                (Some(recipient), _) => hybrid::seal_for_recipient(recipient, &plaintext, &mut OsRng, &mut nonces, |seed, sbox_key, schedule| {
+                    let mut lattice = build_lattice(seed, sbox_key, schedule);
+                    lattice.set_sbox_thresholds(sbox_thresholds.clone());
+                    Ok(lattice)
+                }),
+                (None, Some((seed, hmac_key))) => {
+                    let mut lattice = build_lattice(seed, &hmac_key, KeySchedule::LATEST);
+                    lattice.set_sbox_thresholds(sbox_thresholds);
+                    lattice
+                        .encrypt_with_nonce(&plaintext, seed, &mut nonces)
+                        .and_then(|(nonce, ciphertext)| Container::seal(KdfId::ChaosKey, CipherSuite::LATEST, MacAlgorithm::HmacSha3_256, &hmac_key, nonce, ciphertext))
+                }
+                (None, None) => unreachable!("the chaos key is loaded whenever there is no recipient"),
+            };
+            let container = match sealed {
+                Ok(container) => container,
+                Err(e) => {
+                    eprintln!("Failed to build ciphertext container: {}", e);
+                    process::exit(1);
+                }
+            };
+            let encoded_ciphertext = if binary {
+                container.to_bytes()
+            } else {
+                container.to_armored().into_bytes()
+            };
 
             // Save the ciphertext to the file
-            if let Err(e) = write(ciphertext_file, wrapped_ciphertext) {
+            if let Err(e) = write_output(ciphertext_file, &encoded_ciphertext) {
                 eprintln!("Failed to write ciphertext to file: {}", e);
                 process::exit(1);
             }
 
-            println!("Encryption successful. Ciphertext saved to {}", ciphertext_file);
+            report(ciphertext_file, "Encryption successful. Ciphertext saved to");
         }
         "decrypt" => {
-            if args.len() != 6 {
-                eprintln!("Usage: {} decrypt <bits> <input_file> <ciphertext_file> <decrypted_file>", args[0]);
+            // The identity is a chaos key, whose LWE key pair decapsulates hybrid ciphertexts
+            if let Some(identity_file) = take_option(&mut args, "--identity") {
+                args.insert(2, identity_file);
+            }
+            let bits = take_legacy_bits(&mut args, 5);
+            if args.len() != 5 {
+                eprintln!("Usage: {} decrypt <input_file> <ciphertext_file> <decrypted_file>", args[0]);
+                eprintln!("       {} decrypt --identity <identity_file> <ciphertext_file> <decrypted_file>", args[0]);
                 process::exit(1);
             }
 
-            let bits: usize = match args[2].parse() {
-                Ok(num) => num,
-                Err(_) => {
-                    eprintln!("Invalid bits argument. Must be a positive integer.");
+            let input_file = &args[2];
+            let ciphertext_file = &args[3];
+            let decrypted_file = &args[4];
+
+            let (seed, hmac_key) = load_chaos_key(input_file, bits, passphrase.as_deref());
+
+            // Load the ciphertext container, binary or armored
+            let container = match read_input(ciphertext_file).map_err(Into::into).and_then(|content| Container::parse(&content)) {
+                Ok(container) => container,
+                Err(e) => {
+                    eprintln!("Failed to read ciphertext: {}", e);
                     process::exit(1);
                 }
             };
 
-            let input_file = &args[3];
+            let decrypted = match container.kdf {
+                // Hybrid containers carry their own keys, encapsulated to the chaos key's LWE public key
+                KdfId::LweKem => match hybrid::open_with_identity(&LweKeyPair::from_chaos_seed(seed, &hmac_key), &container, |seed, sbox_key, schedule| Ok(build_lattice(seed, sbox_key, schedule))) {
+                    Ok(decrypted) => decrypted,
+                    Err(montgomery_chaos_lattices::Error::Authentication) => {
+                        eprintln!("HMAC verification failed. The ciphertext may have been tampered with.");
+                        process::exit(1);
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to decrypt the hybrid ciphertext: {}", e);
+                        process::exit(1);
+                    }
+                },
+                KdfId::ChaosKey => {
+                    // Verify HMAC
+                    if container.verify(&hmac_key).is_err() {
+                        eprintln!("HMAC verification failed. The ciphertext may have been tampered with.");
+                        process::exit(1);
+                    }
+
+                    // Decrypt the ciphertext
+                    let lattice = build_lattice(seed, &hmac_key, container.cipher.key_schedule());
+                    match container.cipher {
+                        CipherSuite::LatticeSbox => lattice.decrypt(&container.ciphertext, seed),
+                        _ if container.nonce.len() == NONCE_LENGTH => lattice.decrypt_with_nonce(&container.ciphertext, seed, &container.nonce),
+                        _ => {
+                            eprintln!("Invalid nonce length {}.", container.nonce.len());
+                            process::exit(1);
+                        }
+                    }
+                }
+            };
+
+            // Save the decrypted plaintext to the file
+            if let Err(e) = write_output(decrypted_file, &decrypted) {
+                eprintln!("Failed to write decrypted plaintext to file: {}", e);
+                process::exit(1);
+            }
+
+            report(decrypted_file, "Decryption successful. Plaintext saved to");
+        }
+        "encrypt-stream" => {
+            let bits = take_legacy_bits(&mut args, 5);
+            if args.len() != 5 {
+                eprintln!("Usage: {} encrypt-stream <input_file> <plaintext_file> <ciphertext_file>", args[0]);
+                process::exit(1);
+            }
+
+            let input_file = &args[2];
+            let plaintext_file = &args[3];
             let ciphertext_file = &args[4];
-            let decrypted_file = &args[5];
 
-            // Validate that bits is divisible by 64 and >= 64
-            if bits < 64 || bits % 64 != 0 {
-                eprintln!("Bits must be a multiple of 64 and at least 64.");
+            let (seed, hmac_key) = load_chaos_key(input_file, bits, passphrase.as_deref());
+            let mut lattice = build_lattice(seed, &hmac_key, KeySchedule::LATEST);
+            lattice.set_sbox_thresholds(sbox_thresholds);
+
+            // Encrypt chunk by chunk so memory use does not grow with the input
+            let result = (|| -> montgomery_chaos_lattices::Result<()> {
+                let mut input = open_input(plaintext_file)?;
+                let output = create_output(ciphertext_file)?;
+                let mut writer = EncryptWriter::new(output, &lattice, seed, &hmac_key, &mut NonceGenerator::random())?;
+                io::copy(&mut input, &mut writer)?;
+                writer.finish()?;
+                Ok(())
+            })();
+            if let Err(e) = result {
+                eprintln!("Streaming encryption failed: {}", e);
                 process::exit(1);
             }
 
-            // Decode the chaos key
-            let (seed, hmac_key_prime, _hmac_value) = match decode_chaos_key(bits, input_file) {
-                Ok((s, k, v)) => (s, k, v),
-                Err(e) => {
-                    eprintln!("Failed to decode chaos key: {}", e);
-                    process::exit(1);
-                }
-            };
+            report(ciphertext_file, "Encryption successful. Ciphertext saved to");
+        }
+        "decrypt-stream" => {
+            let bits = take_legacy_bits(&mut args, 5);
+            if args.len() != 5 {
+                eprintln!("Usage: {} decrypt-stream <input_file> <ciphertext_file> <decrypted_file>", args[0]);
+                process::exit(1);
+            }
+
+            let input_file = &args[2];
+            let ciphertext_file = &args[3];
+            let decrypted_file = &args[4];
+
+            let (seed, hmac_key) = load_chaos_key(input_file, bits, passphrase.as_deref());
+
+            // Plaintext from a chunk is only released after its tag verifies
+            let result = (|| -> montgomery_chaos_lattices::Result<()> {
+                let mut input = open_input(ciphertext_file)?;
+                let header = StreamHeader::read(&mut input)?;
+                let lattice = build_lattice(seed, &hmac_key, header.key_schedule());
+                let mut output = create_output(decrypted_file)?;
+                let mut reader = DecryptReader::with_header(input, header, &lattice, seed, &hmac_key)?;
+                io::copy(&mut reader, &mut output)?;
+                output.flush()?;
+                Ok(())
+            })();
+            if let Err(e) = result {
+                eprintln!("Streaming decryption failed: {}", e);
+                process::exit(1);
+            }
 
-            // Load ciphertext (assumed to be in PEM-like format)
-            let wrapped_ciphertext = match read_to_string(ciphertext_file) {
-                Ok(content) => content.trim().to_string(),
+            report(decrypted_file, "Decryption successful. Plaintext saved to");
+        }
+        "stats" => {
+            let length = take_parsed_option(&mut args, "--length", STATS_LENGTH);
+            let bits = take_legacy_bits(&mut args, 3);
+            if args.len() != 3 {
+                eprintln!("Usage: {} stats <input_file> [--length <bytes>]", args[0]);
+                process::exit(1);
+            }
+
+            let input_file = &args[2];
+            let (seed, hmac_key) = load_chaos_key(input_file, bits, passphrase.as_deref());
+
+            // Test the raw chaotic sequence, then the keystream under a fresh nonce
+            let lattice = build_lattice(seed, &hmac_key, KeySchedule::LATEST);
+            let reports = analyze_chaotic_sequence(lattice.sequence_algorithm(), seed, length, STATS_CYCLE_TRIALS).and_then(|sequence| Ok([sequence, analyze_keystream(&lattice, seed, &mut NonceGenerator::random(), length)?]));
+            let reports = match reports {
+                Ok(reports) => reports,
                 Err(e) => {
-                    eprintln!("Failed to read ciphertext file: {}", e);
+                    eprintln!("Statistical tests failed to run: {}", e);
                     process::exit(1);
                 }
             };
 
-            // Extract encoded ciphertext data
-            if !wrapped_ciphertext.starts_with("--- BEGIN CIPHERTEXT ---") || !wrapped_ciphertext.ends_with("--- END CIPHERTEXT ---") {
-                eprintln!("Invalid Ciphertext format.");
+            for report in &reports {
+                println!("{}", report);
+            }
+            if !reports.iter().all(|report| report.passed()) {
                 process::exit(1);
             }
-
-            let encoded_data = wrapped_ciphertext
-                .lines()
-                .skip(1)
-                .take_while(|line| *line != "--- END CIPHERTEXT ---")
-                .collect::<Vec<&str>>()
-                .join("");
-
-            // Separate ciphertext_hex and hmac_value
-            let ciphertext_hex_length = encoded_data.len() - 64; // Assuming HMAC is 64 hex characters
-            let ciphertext_hex = &encoded_data[0..ciphertext_hex_length];
-            let received_hmac = &encoded_data[ciphertext_hex_length..];
-
-            // Verify HMAC
-            let recalculated_hmac = generate_hmac_sha3(ciphertext_hex, &hmac_key_prime.to_bytes_be());
-            if recalculated_hmac != received_hmac {
-                eprintln!("HMAC verification failed. The ciphertext may have been tampered with.");
+        }
+        "chaos-analyze" => {
+            let from: Option<f64> = take_option(&mut args, "--from").map(|value| parse_value("--from", &value));
+            let to: Option<f64> = take_option(&mut args, "--to").map(|value| parse_value("--to", &value));
+            let steps = take_parsed_option(&mut args, "--steps", ANALYZE_STEPS);
+            let length = take_parsed_option(&mut args, "--length", 256);
+            let trials = take_parsed_option(&mut args, "--trials", ANALYZE_TRIALS);
+            if steps == 0 || length == 0 || trials == 0 {
+                eprintln!("--steps, --length and --trials must be at least 1.");
+            }
+            if (args.len() != 4 && args.len() != 5) || steps == 0 || length == 0 || trials == 0 {
+                eprintln!(
+                    "Usage: {} chaos-analyze <lyapunov|bifurcation|divergence|sensitivity> <map|all> [<csv_file>] [--from <p>] [--to <p>] [--steps <n>] [--length <n>] [--trials <n>]",
+                    args[0]
+                );
+                eprintln!("Maps: {}", SequenceAlgorithm::ALL.map(|map| map.name()).join(", "));
                 process::exit(1);
             }
 
-            // Decode ciphertext from hex
-            let ciphertext = match hex::decode(ciphertext_hex) {
-                Ok(bytes) => bytes,
+            let analysis = args[2].as_str();
+            let all = args[3] == "all";
+            let maps = if all {
+                SequenceAlgorithm::ALL.to_vec()
+            } else {
+                match SequenceAlgorithm::from_name(&args[3]) {
+                    Ok(map) => vec![map],
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        process::exit(1);
+                    }
+                }
+            };
+            let output_file = args.get(4).map_or("-", String::as_str);
+
+            let result = (|| -> montgomery_chaos_lattices::Result<String> {
+                match analysis {
+                    "lyapunov" | "bifurcation" => {
+                        let mut exponents = Vec::new();
+                        let mut diagram = Vec::new();
+                        // With `all`, skip the discrete maps that have no real-valued state
+                        for &map in maps.iter().filter(|&&map| !all || default_range(map).is_ok()) {
+                            let (default_from, default_to) = default_range(map)?;
+                            let (from, to) = (from.unwrap_or(default_from), to.unwrap_or(default_to));
+                            if analysis == "lyapunov" {
+                                exponents.extend(lyapunov_sweep(map, from, to, steps, ANALYZE_ITERATIONS)?);
+                            } else {
+                                diagram.extend(bifurcation(map, from, to, steps, ANALYZE_SAMPLES)?);
+                            }
+                        }
+                        Ok(if analysis == "lyapunov" { to_csv(&exponents) } else { to_csv(&diagram) })
+                    }
+                    "divergence" => {
+                        let points = maps.iter().map(|&map| divergence(map, length, steps, trials)).collect::<montgomery_chaos_lattices::Result<Vec<_>>>()?;
+                        Ok(to_csv(&points.concat()))
+                    }
+                    "sensitivity" => {
+                        let points = maps.iter().map(|&map| seed_sensitivity(map, length, trials)).collect::<montgomery_chaos_lattices::Result<Vec<_>>>()?;
+                        Ok(to_csv(&points.concat()))
+                    }
+                    _ => Err(montgomery_chaos_lattices::Error::Format(format!("Unknown analysis {}.", analysis))),
+                }
+            })();
+            let csv = match result {
+                Ok(csv) => csv,
                 Err(e) => {
-                    eprintln!("Failed to decode ciphertext from hex: {}", e);
+                    eprintln!("Chaos analysis failed: {}", e);
                     process::exit(1);
                 }
             };
 
-            // Initialize lattice using the chaos seed and HMAC key
-            let dimensions = 256;
-            let size = 3;
-            let prime_bits = 256;
-            let small_primes = small_prime_sieve(10_000);
-            let scalar = BigUint::from(2u64);
-            let rounds = 40; // Increased for better security
-
-            let mut lattice = Lattice::new(dimensions, size, prime_bits, &small_primes, rounds);
-
-            // Bind lattice with chaos
-            lattice.bind_with_chaos(&scalar, &chaotic_sequence(dimensions, seed));
-
-            // Generate S-Box
-            lattice.generate_sbox(seed);
-
-            // Decrypt the ciphertext
-            let decrypted = lattice.decrypt(&ciphertext, seed);
-
-            // Save the decrypted plaintext to the file
-            if let Err(e) = write(decrypted_file, &decrypted) {
-                eprintln!("Failed to write decrypted plaintext to file: {}", e);
+            if let Err(e) = write_output(output_file, csv.as_bytes()) {
+                eprintln!("Failed to write analysis: {}", e);
+                process::exit(1);
+            }
+            if output_file != "-" {
+                println!("Analysis saved to {}", output_file);
+            }
+        }
+        "lattice-audit" => {
+            let block_size: Option<usize> = take_option(&mut args, "--block-size").map(|value| parse_value("--block-size", &value));
+            let run_bkz = take_flag(&mut args, "--bkz");
+            let dimensions = take_parsed_option(&mut args, "--dimensions", AUDIT_DIMENSIONS);
+            let size = take_parsed_option(&mut args, "--size", dimensions);
+            let bits = take_legacy_bits(&mut args, 3);
+            if args.len() != 3 || dimensions == 0 || size == 0 {
+                eprintln!("Usage: {} lattice-audit <input_file> [--block-size <beta>] [--bkz] [--dimensions <n>] [--size <m>]", args[0]);
                 process::exit(1);
             }
 
-            println!("Decryption successful. Plaintext saved to {}", decrypted_file);
+            let input_file = &args[2];
+            let (seed, hmac_key) = load_chaos_key(input_file, bits, passphrase.as_deref());
+            let lattice = build_lattice_with_shape(seed, &hmac_key, KeySchedule::LATEST, dimensions, size);
+            let block_sizes = block_size.map_or(DEFAULT_BLOCK_SIZES.to_vec(), |block_size| vec![block_size]);
+            let result = (|| -> montgomery_chaos_lattices::Result<()> {
+                print!("{}", estimate(&lattice, &block_sizes)?);
+                if run_bkz {
+                    let params = BkzParams { block_size: block_size.unwrap_or(BkzParams::default().block_size), ..BkzParams::default() };
+                    print!("{}", LatticeBasis::from_lattice(&lattice)?.bkz(&params)?);
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                eprintln!("Lattice audit failed: {}", e);
+                process::exit(1);
+            }
         }
         _ => {
             eprintln!("Invalid command: {}", command);
-            eprintln!("Available commands: gen, verify, encrypt, decrypt");
+            eprintln!("Available commands: gen, keygen-pk, verify, migrate-key, encrypt, decrypt, encrypt-stream, decrypt-stream, stats, chaos-analyze, lattice-audit");
             process::exit(1);
         }
     }
 }
-
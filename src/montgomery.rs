@@ -0,0 +1,317 @@
+//! Montgomery multiplication (REDC) for fixed-width odd moduli.
+//!
+//! A `MontgomeryContext` fixes the modulus `n` and its width in 64-bit
+//! limbs, and precomputes `R = 2^(64 * limbs) mod n`, `R^2 mod n` and
+//! `n' = -n^-1 mod 2^64`. Elements are kept in Montgomery form `x * R mod n`
+//! as exactly `limbs` limbs, so every multiplication runs the same
+//! word-by-word CIOS reduction followed by a masked final subtraction.
+//! `mont_pow` is a Montgomery ladder with conditional swaps over a fixed
+//! number of exponent bits.
+
+use num_bigint::BigUint;
+use num_traits::One;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::error::{Error, Result};
+
+/// An element in Montgomery form, exactly as wide as its context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MontgomeryForm {
+    limbs: Vec<u64>,
+}
+
+impl ConstantTimeEq for MontgomeryForm {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.limbs.ct_eq(&other.limbs)
+    }
+}
+
+/// Precomputed constants for arithmetic modulo a fixed odd modulus
+#[derive(Debug, Clone)]
+pub struct MontgomeryContext {
+    n: BigUint,
+    modulus: Vec<u64>,
+    bits: u64,
+    r: Vec<u64>,
+    r2: Vec<u64>,
+    n_prime: u64,
+}
+
+/// `value` as exactly `limbs` little-endian limbs
+fn to_limbs(value: &BigUint, limbs: usize) -> Vec<u64> {
+    let mut digits = value.to_u64_digits();
+    digits.resize(limbs, 0);
+    digits
+}
+
+/// The value of little-endian `limbs`
+fn from_limbs(limbs: &[u64]) -> BigUint {
+    BigUint::from_slice(&limbs.iter().flat_map(|&limb| [limb as u32, (limb >> 32) as u32]).collect::<Vec<u32>>())
+}
+
+/// `-m^-1 mod 2^64` for odd `m`, by Newton iteration
+fn negated_inverse(m: u64) -> u64 {
+    let mut inverse = 1u64;
+    // Each step doubles the number of correct low bits: 1, 2, 4, ..., 64
+    for _ in 0..6 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(m.wrapping_mul(inverse)));
+    }
+    inverse.wrapping_neg()
+}
+
+impl MontgomeryContext {
+    /// Precompute the constants for an odd modulus greater than one
+    pub fn new(modulus: &BigUint) -> Result<Self> {
+        if modulus <= &BigUint::one() || !modulus.bit(0) {
+            return Err(Error::Lattice("Montgomery arithmetic needs an odd modulus greater than one.".to_string()));
+        }
+        let limbs = modulus.to_u64_digits().len();
+        let r = (BigUint::one() << (64 * limbs)) % modulus;
+        let r2 = (&r * &r) % modulus;
+        Ok(Self {
+            n: modulus.clone(),
+            modulus: to_limbs(modulus, limbs),
+            bits: modulus.bits(),
+            r: to_limbs(&r, limbs),
+            r2: to_limbs(&r2, limbs),
+            n_prime: negated_inverse(modulus.to_u64_digits()[0]),
+        })
+    }
+
+    /// The modulus `n`
+    pub fn modulus(&self) -> BigUint {
+        self.n.clone()
+    }
+
+    /// Width of every element in 64-bit limbs
+    pub fn limbs(&self) -> usize {
+        self.modulus.len()
+    }
+
+    /// `R mod n`, the Montgomery form of one
+    pub fn r(&self) -> BigUint {
+        from_limbs(&self.r)
+    }
+
+    /// `R^2 mod n`
+    pub fn r2(&self) -> BigUint {
+        from_limbs(&self.r2)
+    }
+
+    /// `n' = -n^-1 mod 2^64`
+    pub fn n_prime(&self) -> u64 {
+        self.n_prime
+    }
+
+    /// Montgomery form of one
+    pub fn one(&self) -> MontgomeryForm {
+        MontgomeryForm { limbs: self.r.clone() }
+    }
+
+    /// Convert `x` (reduced mod `n` first) to Montgomery form
+    pub fn to_mont(&self, x: &BigUint) -> MontgomeryForm {
+        let reduced = to_limbs(&(x % &self.n), self.limbs());
+        let mut out = vec![0; self.limbs()];
+        self.mul_into(&reduced, &self.r2, &mut out);
+        MontgomeryForm { limbs: out }
+    }
+
+    /// Convert back from Montgomery form to the canonical value in `[0, n)`
+    pub fn from_mont(&self, x: &MontgomeryForm) -> BigUint {
+        let mut one = vec![0; self.limbs()];
+        one[0] = 1;
+        let mut out = vec![0; self.limbs()];
+        self.mul_into(&x.limbs, &one, &mut out);
+        from_limbs(&out)
+    }
+
+    /// `a * b * R^-1 mod n`
+    pub fn mont_mul(&self, a: &MontgomeryForm, b: &MontgomeryForm) -> MontgomeryForm {
+        let mut out = vec![0; self.limbs()];
+        self.mul_into(&a.limbs, &b.limbs, &mut out);
+        MontgomeryForm { limbs: out }
+    }
+
+    /// `a + b mod n`; addition commutes with the Montgomery form
+    pub fn mont_add(&self, a: &MontgomeryForm, b: &MontgomeryForm) -> MontgomeryForm {
+        let mut out = vec![0; self.limbs()];
+        let mut carry = 0u64;
+        for (j, limb) in out.iter_mut().enumerate() {
+            let sum = a.limbs[j] as u128 + b.limbs[j] as u128 + carry as u128;
+            *limb = sum as u64;
+            carry = (sum >> 64) as u64;
+        }
+        // The sum is below 2n: subtract n unless that would go negative
+        let borrow = sub_limbs(&mut out.clone(), &self.modulus, u64::MAX);
+        let mask = (borrow & !carry & 1).wrapping_sub(1);
+        sub_limbs(&mut out, &self.modulus, mask);
+        MontgomeryForm { limbs: out }
+    }
+
+    /// `a - b mod n`
+    pub fn mont_sub(&self, a: &MontgomeryForm, b: &MontgomeryForm) -> MontgomeryForm {
+        let mut out = a.limbs.clone();
+        let borrow = sub_limbs(&mut out, &b.limbs, u64::MAX);
+        // Add n back if the difference went negative
+        let mask = borrow.wrapping_neg();
+        let mut carry = 0u64;
+        for (j, limb) in out.iter_mut().enumerate() {
+            let sum = *limb as u128 + (self.modulus[j] & mask) as u128 + carry as u128;
+            *limb = sum as u64;
+            carry = (sum >> 64) as u64;
+        }
+        MontgomeryForm { limbs: out }
+    }
+
+    /// `a^2 * R^-1 mod n`
+    pub fn mont_sqr(&self, a: &MontgomeryForm) -> MontgomeryForm {
+        self.mont_mul(a, a)
+    }
+
+    /// `base^exponent` in Montgomery form.
+    ///
+    /// The ladder always runs over the modulus width (or the exponent's
+    /// width, if larger) and swaps with masks, so the sequence of operations
+    /// does not depend on the exponent's value.
+    pub fn mont_pow(&self, base: &MontgomeryForm, exponent: &BigUint) -> MontgomeryForm {
+        let limbs = self.limbs();
+        let mut r0 = self.r.clone();
+        let mut r1 = base.limbs.clone();
+        let mut scratch = vec![0; limbs];
+        let mut swapped = Choice::from(0);
+
+        for i in (0..self.bits.max(exponent.bits())).rev() {
+            let bit = Choice::from(exponent.bit(i) as u8);
+            conditional_swap(&mut r0, &mut r1, swapped ^ bit);
+            swapped = bit;
+
+            // r1 = r0 * r1, r0 = r0^2
+            self.mul_into(&r0, &r1, &mut scratch);
+            std::mem::swap(&mut r1, &mut scratch);
+            self.mul_into(&r0, &r0, &mut scratch);
+            std::mem::swap(&mut r0, &mut scratch);
+        }
+        conditional_swap(&mut r0, &mut r1, swapped);
+        MontgomeryForm { limbs: r0 }
+    }
+
+    /// `base^exponent mod n` for canonical values
+    pub fn pow(&self, base: &BigUint, exponent: &BigUint) -> BigUint {
+        self.from_mont(&self.mont_pow(&self.to_mont(base), exponent))
+    }
+
+    /// CIOS Montgomery multiplication of reduced `a` and `b` into `out`
+    fn mul_into(&self, a: &[u64], b: &[u64], out: &mut [u64]) {
+        let n = self.limbs();
+        let m = &self.modulus;
+        out.fill(0);
+        // The two words above `out` of the running sum
+        let mut top = 0u64;
+
+        for &b_i in b {
+            let mut carry = 0u64;
+            for j in 0..n {
+                let sum = out[j] as u128 + a[j] as u128 * b_i as u128 + carry as u128;
+                out[j] = sum as u64;
+                carry = (sum >> 64) as u64;
+            }
+            let sum = top as u128 + carry as u128;
+            top = sum as u64;
+            let overflow = (sum >> 64) as u64;
+
+            // Add q * m so the lowest word vanishes, then shift down a word
+            let q = out[0].wrapping_mul(self.n_prime);
+            let mut carry = ((out[0] as u128 + q as u128 * m[0] as u128) >> 64) as u64;
+            for j in 1..n {
+                let sum = out[j] as u128 + q as u128 * m[j] as u128 + carry as u128;
+                out[j - 1] = sum as u64;
+                carry = (sum >> 64) as u64;
+            }
+            let sum = top as u128 + carry as u128;
+            out[n - 1] = sum as u64;
+            top = overflow + (sum >> 64) as u64;
+        }
+
+        // The result is below 2n: subtract n unless that would go negative
+        let mut borrow = 0u64;
+        for j in 0..n {
+            let (difference, borrow_a) = out[j].overflowing_sub(m[j]);
+            let (_, borrow_b) = difference.overflowing_sub(borrow);
+            borrow = (borrow_a | borrow_b) as u64;
+        }
+        let mask = (borrow & !top & 1).wrapping_sub(1);
+        let mut borrow = 0u64;
+        for j in 0..n {
+            let (difference, borrow_a) = out[j].overflowing_sub(m[j] & mask);
+            let (difference, borrow_b) = difference.overflowing_sub(borrow);
+            out[j] = difference;
+            borrow = (borrow_a | borrow_b) as u64;
+        }
+    }
+}
+
+/// Subtract `b & mask` from `a` limb-wise, returning the final borrow
+fn sub_limbs(a: &mut [u64], b: &[u64], mask: u64) -> u64 {
+    let mut borrow = 0u64;
+    for (x, &y) in a.iter_mut().zip(b) {
+        let (difference, borrow_a) = x.overflowing_sub(y & mask);
+        let (difference, borrow_b) = difference.overflowing_sub(borrow);
+        *x = difference;
+        borrow = (borrow_a | borrow_b) as u64;
+    }
+    borrow
+}
+
+/// Swap `a` and `b` limb-wise if `choice` is set, without branching
+fn conditional_swap(a: &mut [u64], b: &mut [u64], choice: Choice) {
+    for (x, y) in a.iter_mut().zip(b.iter_mut()) {
+        u64::conditional_swap(x, y, choice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_constants_and_small_modulus() {
+        let context = MontgomeryContext::new(&BigUint::from(97u32)).unwrap();
+        assert_eq!(context.limbs(), 1);
+        assert_eq!(context.r(), (BigUint::one() << 64u32) % 97u32);
+        assert_eq!(context.r2(), (BigUint::one() << 128u32) % 97u32);
+        assert_eq!(context.n_prime().wrapping_mul(97), u64::MAX);
+        assert_eq!(context.pow(&BigUint::from(5u32), &BigUint::from(96u32)), BigUint::one());
+        assert_eq!(context.from_mont(&context.to_mont(&BigUint::from(1000u32))), BigUint::from(1000u32 % 97));
+
+        assert!(MontgomeryContext::new(&BigUint::from(96u32)).is_err());
+        assert!(MontgomeryContext::new(&BigUint::one()).is_err());
+    }
+
+    #[test]
+    fn test_matches_num_bigint() {
+        let mut rng = ChaCha20Rng::seed_from_u64(19);
+        for bits in [64, 65, 256, 1000, 2048] {
+            let modulus = rng.gen_biguint(bits) | BigUint::one() | (BigUint::one() << (bits - 1));
+            let context = MontgomeryContext::new(&modulus).unwrap();
+            for _ in 0..4 {
+                let (a, b) = (rng.gen_biguint_below(&modulus), rng.gen_biguint_below(&modulus));
+                let exponent = rng.gen_biguint(bits);
+                let product = context.mont_mul(&context.to_mont(&a), &context.to_mont(&b));
+                assert_eq!(context.from_mont(&product), &a * &b % &modulus);
+                assert_eq!(context.from_mont(&context.mont_sqr(&context.to_mont(&a))), &a * &a % &modulus);
+                assert_eq!(context.from_mont(&context.mont_add(&context.to_mont(&a), &context.to_mont(&b))), (&a + &b) % &modulus);
+                assert_eq!(context.from_mont(&context.mont_sub(&context.to_mont(&a), &context.to_mont(&b))), (&a + &modulus - &b) % &modulus);
+                assert_eq!(context.pow(&a, &exponent), a.modpow(&exponent, &modulus));
+            }
+            // Values at the top of the range exercise the final subtraction
+            let top = &modulus - 1u32;
+            assert_eq!(context.pow(&top, &BigUint::from(2u32)), BigUint::one());
+            let top_form = context.to_mont(&top);
+            assert_eq!(context.from_mont(&context.mont_add(&top_form, &top_form)), &modulus - 2u32);
+            assert_eq!(context.from_mont(&context.mont_sub(&context.one(), &top_form)), BigUint::from(2u32));
+        }
+    }
+}
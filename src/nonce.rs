@@ -0,0 +1,106 @@
+//! Per-message nonces.
+//!
+//! Every message encrypted under a chaos key needs its own nonce, otherwise
+//! messages share a keystream and S-Box. `NonceGenerator::random` draws them
+//! from the operating system and `NonceGenerator::deterministic` produces a
+//! reproducible sequence for tests. Either records every nonce it hands out
+//! or is given, rejecting any reuse. The encryption entry points only accept
+//! a `Nonce` obtained from a generator, never raw bytes.
+
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::collections::HashSet;
+
+use crate::error::{Error, Result};
+
+/// Nonce length in bytes (128 bits)
+pub const NONCE_LENGTH: usize = 16;
+
+/// A per-message nonce, handed out by a `NonceGenerator`.
+///
+/// It is neither `Copy` nor `Clone`, so each one encrypts a single message.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Nonce([u8; NONCE_LENGTH]);
+
+impl Nonce {
+    /// Get the nonce bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Where nonces come from
+enum Source {
+    Random,
+    Deterministic(Box<ChaCha20Rng>),
+}
+
+/// Hands out per-message nonces
+pub struct NonceGenerator {
+    source: Source,
+    used: HashSet<[u8; NONCE_LENGTH]>,
+}
+
+impl NonceGenerator {
+    /// Draw nonces from the operating system random number generator
+    pub fn random() -> Self {
+        Self {
+            source: Source::Random,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Reproducible nonces for tests; reused nonces are rejected
+    pub fn deterministic(seed: u64) -> Self {
+        Self {
+            source: Source::Deterministic(Box::new(ChaCha20Rng::seed_from_u64(seed))),
+            used: HashSet::new(),
+        }
+    }
+
+    /// Whether this generator runs in deterministic test mode
+    pub fn is_deterministic(&self) -> bool {
+        matches!(self.source, Source::Deterministic(_))
+    }
+
+    /// Produce the nonce for the next message
+    pub fn next_nonce(&mut self) -> Result<Nonce> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        match &mut self.source {
+            Source::Random => getrandom::getrandom(&mut nonce).map_err(|e| Error::Random(e.to_string()))?,
+            Source::Deterministic(rng) => rng.fill_bytes(&mut nonce),
+        }
+        self.claim(nonce)
+    }
+
+    /// Use a caller-chosen nonce, failing if this generator has seen it before
+    pub fn claim(&mut self, nonce: [u8; NONCE_LENGTH]) -> Result<Nonce> {
+        if !self.used.insert(nonce) {
+            return Err(Error::NonceReuse);
+        }
+        Ok(Nonce(nonce))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_nonces_reject_reuse() {
+        let mut a = NonceGenerator::deterministic(1);
+        let mut b = NonceGenerator::deterministic(1);
+        let first = a.next_nonce().unwrap();
+        assert_eq!(first, b.next_nonce().unwrap());
+        assert_ne!(first, a.next_nonce().unwrap());
+
+        assert!(matches!(a.claim(first.0), Err(Error::NonceReuse)));
+        assert!(a.claim([0; NONCE_LENGTH]).is_ok());
+        assert!(matches!(a.claim([0; NONCE_LENGTH]), Err(Error::NonceReuse)));
+
+        let mut random = NonceGenerator::random();
+        let nonce = random.next_nonce().unwrap();
+        assert_ne!(nonce, random.next_nonce().unwrap());
+        assert!(matches!(random.claim(nonce.0), Err(Error::NonceReuse)));
+    }
+}
@@ -1,6 +1,6 @@
 use num_bigint::BigUint;
-use num_traits::{Zero, One};
-use std::ops::{Add, Mul, Sub};
+use num_traits::Zero;
+use std::ops::{Add, Mul};
 
 /// Function to compute the dot product between two lattice vectors
 pub fn dot_product(v1: &[BigUint], v2: &[BigUint]) -> BigUint {
@@ -23,10 +23,14 @@ pub fn test_orthogonal(points: &[Vec<BigUint>]) -> (Vec<u64>, bool) {
     let mut orthogonal = true;
     let mut magnitudes = Vec::new();
 
+    if points.is_empty() {
+        return (magnitudes, orthogonal);
+    }
+
     // Check orthogonality between all pairs of points
     for i in 0..points.len() {
         let magnitude = magnitude_squared(&points[i]);
-        magnitudes.push(magnitude.bits() as u64);
+        magnitudes.push(magnitude.bits());
 
         for j in 0..i {
             let dot = dot_product(&points[i], &points[j]);
@@ -66,9 +70,8 @@ pub fn test_orthogonal(points: &[Vec<BigUint>]) -> (Vec<u64>, bool) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyper_prime::{generate_hyper_prime, small_prime_sieve};
     use rand::rngs::OsRng;
-    use crate::generate_hyper_prime;
-    use crate::small_prime_sieve;
 
     #[test]
     fn test_lattice_orthogonality() {
@@ -82,13 +85,25 @@ mod tests {
         let points: Vec<Vec<BigUint>> = (0..4)
             .map(|_| {
                 (0..dimensions)
-                    .map(|_| generate_hyper_prime(&mut rng, prime_bits, &small_primes, rounds))
+                    .map(|_| generate_hyper_prime(&mut rng, prime_bits, &small_primes, rounds).unwrap())
                     .collect()
             })
             .collect();
 
-        let (_, orthogonal) = test_orthogonality(&points);
-        assert!(orthogonal, "Lattice points are not orthogonal!");
+        // Rows of odd primes always have a non-zero dot product
+        let (magnitudes, orthogonal) = test_orthogonal(&points);
+        assert_eq!(magnitudes.len(), 4);
+        assert!(!orthogonal, "Random prime rows cannot be orthogonal!");
+
+        // The standard basis is orthogonal by construction
+        let basis: Vec<Vec<BigUint>> = (0..dimensions)
+            .map(|i| {
+                (0..dimensions)
+                    .map(|j| if i == j { BigUint::from(1u32) } else { BigUint::zero() })
+                    .collect()
+            })
+            .collect();
+        let (_, orthogonal) = test_orthogonal(&basis);
+        assert!(orthogonal, "Standard basis is not orthogonal!");
     }
 }
-
@@ -0,0 +1,156 @@
+//! Passphrase protection for key material at rest.
+//!
+//! A passphrase is stretched with Argon2id into a ChaCha20-Poly1305 key that
+//! encrypts the payload. The wrapped form is
+//! `MCLW || version || kdf || m_cost || t_cost || p_cost || salt || nonce || ciphertext`,
+//! with the `u32` costs big-endian and everything before the ciphertext
+//! authenticated as associated data.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+use crate::error::{Error, Result};
+
+/// Magic bytes at the start of a wrapped payload
+pub const WRAP_MAGIC: &[u8; 4] = b"MCLW";
+
+/// Wrapped payload format version
+pub const WRAP_VERSION: u8 = 1;
+
+/// KDF identifier for Argon2id
+const KDF_ARGON2ID: u8 = 1;
+
+/// Largest Argon2 memory cost accepted from a wrapped payload, in KiB (2 GiB)
+const MAX_MEMORY_COST: u32 = 2 * 1024 * 1024;
+
+/// Largest Argon2 time cost accepted from a wrapped payload
+const MAX_TIME_COST: u32 = 64;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const HEADER_LENGTH: usize = 4 + 1 + 1 + 4 + 4 + 4 + SALT_LENGTH + NONCE_LENGTH;
+
+/// Argon2id cost parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassphraseParams {
+    /// Memory cost in KiB
+    pub memory_cost: u32,
+    /// Number of passes
+    pub time_cost: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+}
+
+impl Default for PassphraseParams {
+    /// 64 MiB, 3 passes, 1 lane
+    fn default() -> Self {
+        Self {
+            memory_cost: 64 * 1024,
+            time_cost: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Whether `data` starts like a wrapped payload
+pub fn is_wrapped(data: &[u8]) -> bool {
+    data.starts_with(WRAP_MAGIC)
+}
+
+/// Encrypt `plaintext` under a key derived from `passphrase`
+pub fn wrap(plaintext: &[u8], passphrase: &[u8], params: &PassphraseParams) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    getrandom::getrandom(&mut salt).map_err(|e| Error::Random(e.to_string()))?;
+    getrandom::getrandom(&mut nonce).map_err(|e| Error::Random(e.to_string()))?;
+
+    let mut wrapped = Vec::with_capacity(HEADER_LENGTH + plaintext.len() + 16);
+    wrapped.extend_from_slice(WRAP_MAGIC);
+    wrapped.push(WRAP_VERSION);
+    wrapped.push(KDF_ARGON2ID);
+    wrapped.extend_from_slice(&params.memory_cost.to_be_bytes());
+    wrapped.extend_from_slice(&params.time_cost.to_be_bytes());
+    wrapped.extend_from_slice(&params.parallelism.to_be_bytes());
+    wrapped.extend_from_slice(&salt);
+    wrapped.extend_from_slice(&nonce);
+
+    let cipher = derive_cipher(passphrase, &salt, params)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &wrapped })
+        .map_err(|_| Error::Format("Payload is too large to wrap.".to_string()))?;
+    wrapped.extend_from_slice(&ciphertext);
+    Ok(wrapped)
+}
+
+/// Decrypt a payload produced by `wrap`
+pub fn unwrap(wrapped: &[u8], passphrase: &[u8]) -> Result<Vec<u8>> {
+    if wrapped.len() < HEADER_LENGTH {
+        return Err(Error::Length {
+            expected: HEADER_LENGTH,
+            found: wrapped.len(),
+        });
+    }
+    if !is_wrapped(wrapped) {
+        return Err(Error::Format("Missing wrapped key magic bytes.".to_string()));
+    }
+    if wrapped[4] != WRAP_VERSION {
+        return Err(Error::Format(format!("Unsupported wrapped key version {}.", wrapped[4])));
+    }
+    if wrapped[5] != KDF_ARGON2ID {
+        return Err(Error::Format(format!("Unknown passphrase KDF {}.", wrapped[5])));
+    }
+
+    let read_u32 = |offset: usize| u32::from_be_bytes(wrapped[offset..offset + 4].try_into().unwrap());
+    let params = PassphraseParams {
+        memory_cost: read_u32(6),
+        time_cost: read_u32(10),
+        parallelism: read_u32(14),
+    };
+    if params.memory_cost > MAX_MEMORY_COST || params.time_cost > MAX_TIME_COST {
+        return Err(Error::Format("Passphrase KDF costs are out of range.".to_string()));
+    }
+    let (header, ciphertext) = wrapped.split_at(HEADER_LENGTH);
+    let salt = &header[18..18 + SALT_LENGTH];
+    let nonce = &header[18 + SALT_LENGTH..];
+
+    let cipher = derive_cipher(passphrase, salt, &params)?;
+    cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| Error::Authentication)
+}
+
+/// Stretch the passphrase with Argon2id into an AEAD instance
+fn derive_cipher(passphrase: &[u8], salt: &[u8], params: &PassphraseParams) -> Result<ChaCha20Poly1305> {
+    let argon2_params = Params::new(params.memory_cost, params.time_cost, params.parallelism, Some(32))
+        .map_err(|e| Error::Format(format!("Invalid passphrase KDF costs: {}", e)))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+        .hash_password_into(passphrase, salt, &mut key)
+        .map_err(|e| Error::Format(format!("Passphrase key derivation failed: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(&key.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: PassphraseParams = PassphraseParams {
+        memory_cost: 64,
+        time_cost: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_wrap_unwrap() {
+        let wrapped = wrap(b"key material", b"correct horse", &FAST).unwrap();
+        assert!(is_wrapped(&wrapped));
+        assert_eq!(unwrap(&wrapped, b"correct horse").unwrap(), b"key material");
+        assert!(matches!(unwrap(&wrapped, b"wrong horse"), Err(Error::Authentication)));
+
+        // The cost parameters are bound to the ciphertext
+        let mut tampered = wrapped.clone();
+        tampered[13] ^= 1;
+        assert!(unwrap(&tampered, b"correct horse").is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::{Error, Result};
+
+/// Wrap the key or ciphertext in PEM-like format with line breaks every 64 characters
+pub fn wrap_in_pem_format(label: &str, key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let wrapped_key: String = chars
+        .chunks(64)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    format!(
+        "--- BEGIN {} ---\n{}\n--- END {} ---",
+        label, wrapped_key, label
+    )
+}
+
+/// Extract the encoded data between the PEM-like markers for `label`
+pub fn unwrap_pem_format(label: &str, content: &str) -> Result<String> {
+    let begin = format!("--- BEGIN {} ---", label);
+    let end = format!("--- END {} ---", label);
+
+    let content = content.trim();
+    if !content.starts_with(&begin) || !content.ends_with(&end) {
+        return Err(Error::Format(format!("Missing {} markers.", label)));
+    }
+
+    let encoded_data = content
+        .lines()
+        .skip(1)
+        .take_while(|line| *line != end)
+        .collect::<Vec<&str>>()
+        .join("");
+
+    Ok(encoded_data)
+}
@@ -0,0 +1,270 @@
+//! Cryptanalytic quality metrics for 8-bit S-Boxes.
+//!
+//! Nonlinearity and the linear approximation table come from the
+//! Walsh–Hadamard spectrum of every component function, differential
+//! uniformity from the difference distribution table, and the avalanche
+//! metrics from flipping each input bit in turn.
+//!
+//! Weak S-Boxes are rejected per message rather than per key: every nonce
+//! cipher suite derives a new S-Box from the key, the lattice anchors and
+//! the nonce, so a key-level check would test a table no message uses.
+//! `Lattice::encrypt_with_nonce` redraws the nonce until its S-Box passes
+//! the lattice's `SboxThresholds`, which the CLI takes from its
+//! `--min-nonlinearity` and related options.
+
+use crate::error::{Error, Result};
+
+/// Summary of an S-Box's quality metrics
+#[derive(Debug, Clone, PartialEq)]
+pub struct SboxReport {
+    /// Minimum nonlinearity over all non-zero component functions (ideal 8-bit permutation: 112)
+    pub nonlinearity: u32,
+    /// Largest DDT entry with a non-zero input difference (ideal: 4)
+    pub differential_uniformity: u32,
+    /// Largest absolute LAT entry with a non-zero output mask
+    pub max_linear_bias: u32,
+    /// Largest deviation from 0.5 of any output bit flip probability (SAC)
+    pub sac_max_deviation: f64,
+    /// Largest absolute correlation between two output bit flips (BIC)
+    pub bic_max_correlation: f64,
+    /// Number of inputs with `S(x) == x`
+    pub fixed_points: usize,
+    /// Number of inputs with `S(x) == !x`
+    pub opposite_fixed_points: usize,
+}
+
+/// Limits below which an S-Box is rejected as weak
+#[derive(Debug, Clone, PartialEq)]
+pub struct SboxThresholds {
+    pub min_nonlinearity: u32,
+    pub max_differential_uniformity: u32,
+    pub max_sac_deviation: f64,
+    pub max_bic_correlation: f64,
+    /// Limit on fixed and opposite fixed points combined
+    pub max_fixed_points: usize,
+}
+
+impl Default for SboxThresholds {
+    /// Limits that nearly all random permutations pass; they catch
+    /// degenerate, near-linear or near-identity tables
+    fn default() -> Self {
+        Self {
+            min_nonlinearity: 84,
+            max_differential_uniformity: 16,
+            max_sac_deviation: 0.25,
+            max_bic_correlation: 0.45,
+            max_fixed_points: 8,
+        }
+    }
+}
+
+impl SboxReport {
+    /// Return an error naming the first metric that violates `thresholds`
+    pub fn check(&self, thresholds: &SboxThresholds) -> Result<()> {
+        if self.nonlinearity < thresholds.min_nonlinearity {
+            return Err(Error::WeakSbox(format!("nonlinearity {} is below {}", self.nonlinearity, thresholds.min_nonlinearity)));
+        }
+        if self.differential_uniformity > thresholds.max_differential_uniformity {
+            return Err(Error::WeakSbox(format!(
+                "differential uniformity {} exceeds {}",
+                self.differential_uniformity, thresholds.max_differential_uniformity
+            )));
+        }
+        if self.sac_max_deviation > thresholds.max_sac_deviation {
+            return Err(Error::WeakSbox(format!("SAC deviation {:.3} exceeds {:.3}", self.sac_max_deviation, thresholds.max_sac_deviation)));
+        }
+        if self.bic_max_correlation > thresholds.max_bic_correlation {
+            return Err(Error::WeakSbox(format!("BIC correlation {:.3} exceeds {:.3}", self.bic_max_correlation, thresholds.max_bic_correlation)));
+        }
+        let fixed_points = self.fixed_points + self.opposite_fixed_points;
+        if fixed_points > thresholds.max_fixed_points {
+            return Err(Error::WeakSbox(format!("{} fixed points exceed {}", fixed_points, thresholds.max_fixed_points)));
+        }
+        Ok(())
+    }
+}
+
+/// Compute every metric for `sbox`
+pub fn analyze_sbox(sbox: &[u8; 256]) -> SboxReport {
+    let lat = linear_approximation_table(sbox);
+    let max_linear_bias = lat
+        .iter()
+        .skip(1)
+        .flat_map(|row| row.iter())
+        .map(|&entry| entry.unsigned_abs() as u32)
+        .max()
+        .unwrap_or(0);
+
+    let (sac_max_deviation, bic_max_correlation) = avalanche(sbox);
+
+    SboxReport {
+        nonlinearity: nonlinearity(sbox),
+        differential_uniformity: differential_uniformity(sbox),
+        max_linear_bias,
+        sac_max_deviation,
+        bic_max_correlation,
+        fixed_points: (0..256).filter(|&x| sbox[x] as usize == x).count(),
+        opposite_fixed_points: (0..256).filter(|&x| sbox[x] as usize == x ^ 0xff).count(),
+    }
+}
+
+/// In-place fast Walsh–Hadamard transform
+fn walsh_hadamard(spectrum: &mut [i32; 256]) {
+    let mut step = 1;
+    while step < 256 {
+        for block in (0..256).step_by(2 * step) {
+            for i in block..block + step {
+                let (a, b) = (spectrum[i], spectrum[i + step]);
+                spectrum[i] = a + b;
+                spectrum[i + step] = a - b;
+            }
+        }
+        step *= 2;
+    }
+}
+
+/// Walsh spectrum of the component function `x -> parity(mask & S(x))`
+fn component_spectrum(sbox: &[u8; 256], mask: u8) -> [i32; 256] {
+    let mut spectrum: [i32; 256] = std::array::from_fn(|x| if (sbox[x] & mask).count_ones().is_multiple_of(2) { 1 } else { -1 });
+    walsh_hadamard(&mut spectrum);
+    spectrum
+}
+
+/// Minimum nonlinearity over all non-zero component functions
+pub fn nonlinearity(sbox: &[u8; 256]) -> u32 {
+    (1..=255u8)
+        .map(|mask| {
+            let peak = component_spectrum(sbox, mask).iter().map(|w| w.unsigned_abs()).max().unwrap_or(0);
+            128 - peak / 2
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// Linear approximation table indexed `[output_mask][input_mask]`.
+///
+/// Each entry is `#{x : a·x = b·S(x)} - 128`.
+pub fn linear_approximation_table(sbox: &[u8; 256]) -> Vec<[i16; 256]> {
+    (0..=255u8)
+        .map(|mask| {
+            let spectrum = component_spectrum(sbox, mask);
+            std::array::from_fn(|a| (spectrum[a] / 2) as i16)
+        })
+        .collect()
+}
+
+/// Difference distribution table indexed `[input_difference][output_difference]`
+pub fn difference_distribution_table(sbox: &[u8; 256]) -> Vec<[u16; 256]> {
+    (0..256)
+        .map(|a| {
+            let mut row = [0u16; 256];
+            for x in 0..256 {
+                row[(sbox[x] ^ sbox[x ^ a]) as usize] += 1;
+            }
+            row
+        })
+        .collect()
+}
+
+/// Largest DDT entry with a non-zero input difference
+pub fn differential_uniformity(sbox: &[u8; 256]) -> u32 {
+    difference_distribution_table(sbox)
+        .iter()
+        .skip(1)
+        .flat_map(|row| row.iter())
+        .map(|&entry| entry as u32)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Strict avalanche and bit independence: the largest deviation of any
+/// flip probability from 0.5, and the largest correlation between two
+/// output bit flips under a single input bit flip
+fn avalanche(sbox: &[u8; 256]) -> (f64, f64) {
+    let mut sac_max_deviation: f64 = 0.0;
+    let mut bic_max_correlation: f64 = 0.0;
+
+    for input_bit in 0..8 {
+        let flips: Vec<u8> = (0..256).map(|x| sbox[x] ^ sbox[x ^ (1 << input_bit)]).collect();
+        let bit = |flip: u8, j: usize| ((flip >> j) & 1) as f64;
+
+        for j in 0..8 {
+            let probability = flips.iter().map(|&flip| bit(flip, j)).sum::<f64>() / 256.0;
+            sac_max_deviation = sac_max_deviation.max((probability - 0.5).abs());
+        }
+
+        for j in 0..8 {
+            for k in j + 1..8 {
+                let correlation = pearson(flips.iter().map(|&flip| (bit(flip, j), bit(flip, k))));
+                bic_max_correlation = bic_max_correlation.max(correlation.abs());
+            }
+        }
+    }
+
+    (sac_max_deviation, bic_max_correlation)
+}
+
+/// Pearson correlation of paired samples, 0 when either side is constant
+fn pearson(samples: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let n = samples.clone().count() as f64;
+    let (sum_x, sum_y) = samples.clone().fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+    let (cov, var_x, var_y) = samples.fold((0.0, 0.0, 0.0), |(c, vx, vy), (x, y)| {
+        (c + (x - mean_x) * (y - mean_y), vx + (x - mean_x).powi(2), vy + (y - mean_y).powi(2))
+    });
+    if var_x == 0.0 || var_y == 0.0 {
+        0.0
+    } else {
+        cov / (var_x * var_y).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Forward S-Box of AES (FIPS 197)
+    fn aes_sbox() -> [u8; 256] {
+        // Multiplicative inverse in GF(2^8) followed by the affine map
+        let multiply = |mut a: u8, mut b: u8| {
+            let mut product = 0u8;
+            while b != 0 {
+                if b & 1 != 0 {
+                    product ^= a;
+                }
+                a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+                b >>= 1;
+            }
+            product
+        };
+        std::array::from_fn(|x| {
+            let inverse = (0..=255u8).find(|&y| multiply(x as u8, y) == 1).unwrap_or(0);
+            inverse ^ inverse.rotate_left(1) ^ inverse.rotate_left(2) ^ inverse.rotate_left(3) ^ inverse.rotate_left(4) ^ 0x63
+        })
+    }
+
+    #[test]
+    fn test_aes_sbox_metrics() {
+        let sbox = aes_sbox();
+        assert_eq!(sbox[0x00], 0x63);
+        assert_eq!(sbox[0x53], 0xed);
+
+        let report = analyze_sbox(&sbox);
+        assert_eq!(report.nonlinearity, 112);
+        assert_eq!(report.differential_uniformity, 4);
+        assert_eq!(report.max_linear_bias, 16);
+        assert_eq!(report.fixed_points, 0);
+        assert_eq!(report.opposite_fixed_points, 0);
+        assert!(report.check(&SboxThresholds::default()).is_ok());
+    }
+
+    #[test]
+    fn test_identity_sbox_is_rejected() {
+        let identity: [u8; 256] = std::array::from_fn(|x| x as u8);
+        let report = analyze_sbox(&identity);
+        assert_eq!(report.nonlinearity, 0);
+        assert_eq!(report.differential_uniformity, 256);
+        assert_eq!(report.fixed_points, 256);
+        assert!(matches!(report.check(&SboxThresholds::default()), Err(Error::WeakSbox(_))));
+    }
+}
@@ -0,0 +1,429 @@
+//! Statistical randomness tests in the spirit of NIST SP 800-22.
+//!
+//! The bit-level tests (monobit, block frequency, runs, serial, approximate
+//! entropy and cumulative sums) follow the formulas of SP 800-22 section 2
+//! and report a p-value; a test passes when `p >= SIGNIFICANCE`. The cycle
+//! structure test compares the cycle lengths of many chaotic permutations
+//! with those of uniformly random permutations, where a permutation of `n`
+//! elements has on average `1/k` cycles of length `k`.
+
+use std::fmt;
+
+use crate::chaos::SequenceAlgorithm;
+use crate::error::{Error, Result};
+use crate::lattice::Lattice;
+use crate::nonce::NonceGenerator;
+
+/// Significance level below which a test fails
+pub const SIGNIFICANCE: f64 = 0.01;
+
+/// Fewest bits the battery accepts
+pub const MIN_BITS: usize = 1000;
+
+/// Outcome of one test
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub name: String,
+    pub p_value: f64,
+    pub passed: bool,
+}
+
+impl TestResult {
+    fn new(name: &str, p_value: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            p_value,
+            passed: p_value >= SIGNIFICANCE,
+        }
+    }
+}
+
+/// Pass/fail report of a test battery
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsReport {
+    pub title: String,
+    pub results: Vec<TestResult>,
+}
+
+impl StatsReport {
+    /// Whether every test passed
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.passed)
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.title)?;
+        for result in &self.results {
+            let verdict = if result.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "  {:<28} p = {:.6}  {}", result.name, result.p_value, verdict)?;
+        }
+        write!(f, "  {}", if self.passed() { "All tests passed." } else { "Some tests failed." })
+    }
+}
+
+/// Expand bytes into bits, most significant bit first
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1))
+        .collect()
+}
+
+/// Frequency (monobit) test
+pub fn monobit(bits: &[u8]) -> f64 {
+    let n = bits.len() as f64;
+    let sum: i64 = bits.iter().map(|&bit| if bit == 1 { 1 } else { -1 }).sum();
+    erfc(sum.unsigned_abs() as f64 / n.sqrt() / std::f64::consts::SQRT_2)
+}
+
+/// Frequency test within blocks of `block_length` bits
+pub fn block_frequency(bits: &[u8], block_length: usize) -> f64 {
+    let blocks = bits.len() / block_length;
+    let chi_squared: f64 = bits
+        .chunks_exact(block_length)
+        .map(|block| {
+            let proportion = block.iter().map(|&bit| bit as f64).sum::<f64>() / block_length as f64;
+            (proportion - 0.5).powi(2)
+        })
+        .sum::<f64>()
+        * 4.0
+        * block_length as f64;
+    igamc(blocks as f64 / 2.0, chi_squared / 2.0)
+}
+
+/// Runs test
+pub fn runs(bits: &[u8]) -> f64 {
+    let n = bits.len() as f64;
+    let proportion = bits.iter().map(|&bit| bit as f64).sum::<f64>() / n;
+    // The frequency prerequisite of the runs test
+    if (proportion - 0.5).abs() >= 2.0 / n.sqrt() {
+        return 0.0;
+    }
+    let observed_runs = 1 + bits.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    let expected = 2.0 * n * proportion * (1.0 - proportion);
+    erfc((observed_runs as f64 - expected).abs() / (2.0 * (2.0 * n).sqrt() * proportion * (1.0 - proportion)))
+}
+
+/// Frequencies of all overlapping `m`-bit patterns, wrapping around the end
+fn pattern_counts(bits: &[u8], m: usize) -> Vec<u64> {
+    let mut counts = vec![0u64; 1 << m];
+    for start in 0..bits.len() {
+        let pattern = (0..m).fold(0usize, |acc, offset| (acc << 1) | bits[(start + offset) % bits.len()] as usize);
+        counts[pattern] += 1;
+    }
+    counts
+}
+
+/// The `psi^2_m` statistic of the serial test
+fn psi_squared(bits: &[u8], m: usize) -> f64 {
+    if m == 0 {
+        return 0.0;
+    }
+    let n = bits.len() as f64;
+    let sum: f64 = pattern_counts(bits, m).iter().map(|&count| (count as f64).powi(2)).sum();
+    (1u64 << m) as f64 / n * sum - n
+}
+
+/// Serial test with pattern length `m` (at least 3), returning both p-values
+pub fn serial(bits: &[u8], m: usize) -> (f64, f64) {
+    let psi_m = psi_squared(bits, m);
+    let psi_m1 = psi_squared(bits, m - 1);
+    let psi_m2 = psi_squared(bits, m - 2);
+    let delta = psi_m - psi_m1;
+    let delta_squared = psi_m - 2.0 * psi_m1 + psi_m2;
+    (
+        igamc((1u64 << (m - 2)) as f64, delta / 2.0),
+        igamc((1u64 << (m - 3)) as f64, delta_squared / 2.0),
+    )
+}
+
+/// Approximate entropy test with block length `m`
+pub fn approximate_entropy(bits: &[u8], m: usize) -> f64 {
+    let n = bits.len() as f64;
+    let phi = |m: usize| -> f64 {
+        if m == 0 {
+            return 0.0;
+        }
+        pattern_counts(bits, m)
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let frequency = count as f64 / n;
+                frequency * frequency.ln()
+            })
+            .sum()
+    };
+    let apen = phi(m) - phi(m + 1);
+    let chi_squared = 2.0 * n * (std::f64::consts::LN_2 - apen);
+    igamc((1u64 << m) as f64 / 2.0, chi_squared / 2.0)
+}
+
+/// Cumulative sums test, forward or backward
+pub fn cumulative_sums(bits: &[u8], forward: bool) -> f64 {
+    let n = bits.len() as i64;
+    let steps: Box<dyn Iterator<Item = &u8>> = if forward { Box::new(bits.iter()) } else { Box::new(bits.iter().rev()) };
+    let mut sum = 0i64;
+    let mut z = 0i64;
+    for &bit in steps {
+        sum += if bit == 1 { 1 } else { -1 };
+        z = z.max(sum.abs());
+    }
+    if z == 0 {
+        return 0.0;
+    }
+
+    let root_n = (n as f64).sqrt();
+    let zf = z as f64;
+    // Integer division truncates toward zero, as in the reference implementation
+    let first: f64 = ((-n / z + 1) / 4..=(n / z - 1) / 4)
+        .map(|k| normal_cdf((4 * k + 1) as f64 * zf / root_n) - normal_cdf((4 * k - 1) as f64 * zf / root_n))
+        .sum();
+    let second: f64 = ((-n / z - 3) / 4..=(n / z - 1) / 4)
+        .map(|k| normal_cdf((4 * k + 3) as f64 * zf / root_n) - normal_cdf((4 * k + 1) as f64 * zf / root_n))
+        .sum();
+    1.0 - first + second
+}
+
+/// Chi-square test of the cycle lengths of permutations of `0..n`
+pub fn cycle_structure(permutations: &[Vec<usize>]) -> Result<f64> {
+    let n = permutations.first().map(Vec::len).unwrap_or(0);
+    if n < 2 {
+        return Err(Error::Format("Cycle structure test needs permutations of at least 2 elements.".to_string()));
+    }
+
+    // Bucket b holds cycle lengths in (2^(b-1), 2^b]
+    let buckets = n.next_power_of_two().trailing_zeros() as usize + 1;
+    let bucket = |length: usize| length.next_power_of_two().trailing_zeros() as usize;
+    let mut observed = vec![0f64; buckets];
+    for permutation in permutations {
+        for length in cycle_lengths(permutation)? {
+            observed[bucket(length)] += 1.0;
+        }
+    }
+
+    let mut expected = vec![0f64; buckets];
+    for length in 1..=n {
+        expected[bucket(length)] += permutations.len() as f64 / length as f64;
+    }
+
+    let chi_squared: f64 = observed.iter().zip(&expected).map(|(o, e)| (o - e).powi(2) / e).sum();
+    Ok(igamc((buckets - 1) as f64 / 2.0, chi_squared / 2.0))
+}
+
+/// Lengths of the cycles of a permutation of `0..n`
+fn cycle_lengths(permutation: &[usize]) -> Result<Vec<usize>> {
+    let mut visited = vec![false; permutation.len()];
+    let mut lengths = Vec::new();
+    for start in 0..permutation.len() {
+        let mut length = 0;
+        let mut position = start;
+        while !visited[position] {
+            visited[position] = true;
+            position = *permutation
+                .get(position)
+                .filter(|&&next| next < permutation.len())
+                .ok_or_else(|| Error::Format("Sequence is not a permutation.".to_string()))?;
+            length += 1;
+        }
+        if length > 0 {
+            if position != start {
+                return Err(Error::Format("Sequence is not a permutation.".to_string()));
+            }
+            lengths.push(length);
+        }
+    }
+    Ok(lengths)
+}
+
+/// Run every bit-level test on `bits` with parameters scaled to its length
+pub fn analyze_bits(title: &str, bits: &[u8]) -> Result<StatsReport> {
+    if bits.len() < MIN_BITS {
+        return Err(Error::Length {
+            expected: MIN_BITS,
+            found: bits.len(),
+        });
+    }
+
+    let log2_n = bits.len().ilog2() as usize;
+    let serial_m = (log2_n - 3).clamp(3, 16);
+    let entropy_m = (log2_n - 6).clamp(1, 10);
+    let (serial_p1, serial_p2) = serial(bits, serial_m);
+
+    Ok(StatsReport {
+        title: format!("{} ({} bits)", title, bits.len()),
+        results: vec![
+            TestResult::new("monobit", monobit(bits)),
+            TestResult::new("block frequency (M=128)", block_frequency(bits, 128)),
+            TestResult::new("runs", runs(bits)),
+            TestResult::new(&format!("serial p1 (m={})", serial_m), serial_p1),
+            TestResult::new(&format!("serial p2 (m={})", serial_m), serial_p2),
+            TestResult::new(&format!("approximate entropy (m={})", entropy_m), approximate_entropy(bits, entropy_m)),
+            TestResult::new("cumulative sums (forward)", cumulative_sums(bits, true)),
+            TestResult::new("cumulative sums (backward)", cumulative_sums(bits, false)),
+        ],
+    })
+}
+
+/// Test the low bytes of a `length`-element chaotic sequence, as consumed by
+/// the keystream, and the cycle structure of `trials` 256-element sequences
+pub fn analyze_chaotic_sequence(algorithm: SequenceAlgorithm, seed: u64, length: usize, trials: u64) -> Result<StatsReport> {
+    let bytes: Vec<u8> = algorithm.sequence(length, seed).iter().map(|&value| value as u8).collect();
+    let mut report = analyze_bits(&format!("{:?} chaotic sequence", algorithm), &bytes_to_bits(&bytes))?;
+
+    let permutations: Vec<Vec<usize>> = (0..trials).map(|i| algorithm.sequence(256, seed.wrapping_add(i))).collect();
+    report
+        .results
+        .push(TestResult::new(&format!("cycle structure ({} x 256)", trials), cycle_structure(&permutations)?));
+    Ok(report)
+}
+
+/// Test the output of `Lattice::encrypt_with_nonce` over `length` zero bytes
+pub fn analyze_keystream(lattice: &Lattice, seed: u64, nonces: &mut NonceGenerator, length: usize) -> Result<StatsReport> {
+    let (_, keystream) = lattice.encrypt_with_nonce(&vec![0u8; length], seed, nonces)?;
+    analyze_bits("Lattice keystream", &bytes_to_bits(&keystream))
+}
+
+/// Welch's t statistic for the difference in means of two samples with
+/// possibly unequal variances, as used by dudect-style timing tests
+pub fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+    let moments = |samples: &[f64]| {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (n, mean, variance)
+    };
+    let (n_a, mean_a, var_a) = moments(a);
+    let (n_b, mean_b, var_b) = moments(b);
+    (mean_a - mean_b) / (var_a / n_a + var_b / n_b).sqrt()
+}
+
+/// Standard normal cumulative distribution function
+fn normal_cdf(z: f64) -> f64 {
+    0.5 * erfc(-z / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function
+fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        2.0 - igamc(0.5, x * x)
+    } else {
+        igamc(0.5, x * x)
+    }
+}
+
+/// Upper regularized incomplete gamma function Q(a, x)
+fn igamc(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        // Series for P(a, x)
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut ap = a;
+        for _ in 0..10_000 {
+            ap += 1.0;
+            term *= x / ap;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        (1.0 - sum * prefactor).max(0.0)
+    } else {
+        // Continued fraction for Q(a, x) by the modified Lentz method
+        const TINY: f64 = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..10_000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        prefactor * h
+    }
+}
+
+/// Natural logarithm of the gamma function (Lanczos approximation)
+pub(crate) fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection formula
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, &c)| acc + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(s: &str) -> Vec<u8> {
+        s.bytes().map(|b| b - b'0').collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    // Worked examples from NIST SP 800-22 Rev. 1a, section 2
+    #[test]
+    fn test_sp800_22_examples() {
+        assert_close(monobit(&bits("1011010101")), 0.527089);
+        assert_close(block_frequency(&bits("0110011010"), 3), 0.801252);
+        assert_close(runs(&bits("1001101011")), 0.147232);
+        let (p1, p2) = serial(&bits("0011011101"), 3);
+        assert_close(p1, 0.808792);
+        assert_close(p2, 0.670320);
+        assert_close(approximate_entropy(&bits("0100110101"), 3), 0.261961);
+        assert_close(cumulative_sums(&bits("1011010111"), true), 0.411658);
+        // Not from SP 800-22: (2.5 - 5) / sqrt(5/12 + 20/12) = -sqrt(3)
+        assert_close(welch_t(&[1.0, 2.0, 3.0, 4.0], &[2.0, 4.0, 6.0, 8.0]), -3f64.sqrt());
+    }
+
+    #[test]
+    fn test_battery_flags_biased_input() {
+        let biased = vec![1u8; 4096];
+        let report = analyze_bits("ones", &biased).unwrap();
+        assert!(!report.passed());
+        assert!(report.to_string().contains("FAIL"));
+
+        let identity: Vec<Vec<usize>> = (0..100).map(|_| (0..256).collect()).collect();
+        assert!(cycle_structure(&identity).unwrap() < SIGNIFICANCE);
+        assert!(cycle_structure(&[vec![0, 0, 1]]).is_err());
+    }
+}
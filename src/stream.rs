@@ -0,0 +1,486 @@
+//! Streaming chunked encryption.
+//!
+//! A stream starts with a header (`MCLS`, a version byte, the chunk size and
+//! a per-stream nonce) followed by frames of the form
+//! `flags || counter || length || ciphertext || tag`. Each chunk is encrypted
+//! like `Lattice::encrypt_with_nonce` under the stream nonce and a seed derived
+//! from the chaos seed and the chunk counter, and authenticated with an
+//! HMAC-SHA3-256 tag over the whole stream header, the frame header and the
+//! ciphertext, so a changed version or chunk size fails the first tag.
+//! Version 6 streams use `KeySchedule::LATEST`: the 256-bit
+//! `SequenceAlgorithm::WideCatMap` with the constant-time lattice binding.
+//! All their chunks share one S-Box, derived from the chaos seed and the
+//! stream nonce, and the nonce is drawn until that S-Box passes the lattice's
+//! thresholds; older versions derive a new S-Box for every chunk, and their
+//! tags cover only the nonce of the stream header. Version 5 streams (the
+//! same schedule), version 4 streams (the same sequence with the legacy binding), version 3
+//! streams (`SequenceAlgorithm::CatMap`), version 2 streams (the
+//! trigonometric sequence) and version 1 streams, which also have no nonce,
+//! can still be read. The last frame carries `FINAL_CHUNK`, so truncation and
+//! reordering are detected.
+
+use sha3::{Digest, Sha3_256};
+use std::io::{self, Read, Write};
+
+use crate::chaos::SequenceAlgorithm;
+use crate::error::{Error, Result};
+use crate::lattice::{Binding, KeySchedule, Lattice};
+use crate::mac::{Mac, MacAlgorithm};
+use crate::nonce::{Nonce, NonceGenerator, NONCE_LENGTH};
+
+/// Magic bytes at the start of every stream
+pub const STREAM_MAGIC: &[u8; 4] = b"MCLS";
+
+/// Stream format version written by this release
+pub const STREAM_VERSION: u8 = 6;
+
+/// Stream format version with the constant-time binding and an unauthenticated header
+pub const CONSTANT_TIME_STREAM_VERSION: u8 = 5;
+
+/// Stream format version with a nonce, the 256-bit cat map sequence and the legacy binding
+pub const WIDE_CAT_MAP_STREAM_VERSION: u8 = 4;
+
+/// Stream format version with a nonce and the 64-bit cat map sequence
+pub const CAT_MAP_STREAM_VERSION: u8 = 3;
+
+/// Stream format version with a nonce and the trigonometric sequence
+pub const TRIGONOMETRIC_STREAM_VERSION: u8 = 2;
+
+/// Stream format version without a nonce
+pub const LEGACY_STREAM_VERSION: u8 = 1;
+
+/// Default plaintext chunk size in bytes
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Largest chunk size a reader will accept from a stream header
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Frame flag marking the last chunk of a stream
+pub const FINAL_CHUNK: u8 = 0x01;
+
+const HEADER_LENGTH: usize = 4 + 1 + 4;
+const FRAME_HEADER_LENGTH: usize = 1 + 8 + 4;
+const TAG_LENGTH: usize = 32;
+
+/// Derive the keystream seed for a chunk from the chaos seed and its counter
+fn chunk_seed(seed: u64, counter: u64) -> u64 {
+    let mut hasher = Sha3_256::new();
+    hasher.update(b"chunk");
+    hasher.update(seed.to_be_bytes());
+    hasher.update(counter.to_be_bytes());
+    let hash = hasher.finalize();
+    u64::from_be_bytes(hash[0..8].try_into().unwrap())
+}
+
+/// Derive the S-Box seed for a chunk: version 6 streams have one S-Box per stream
+fn chunk_sbox_seed(version: u8, seed: u64, counter: u64) -> u64 {
+    if version >= STREAM_VERSION {
+        seed
+    } else {
+        chunk_seed(seed, counter)
+    }
+}
+
+/// MAC used to authenticate every frame
+fn chunk_mac(mac_key: &[u8]) -> Mac {
+    Mac::new(MacAlgorithm::HmacSha3_256, mac_key)
+}
+
+/// Encode a frame header
+fn frame_header(flags: u8, counter: u64, length: usize) -> [u8; FRAME_HEADER_LENGTH] {
+    let mut header = [0u8; FRAME_HEADER_LENGTH];
+    header[0] = flags;
+    header[1..9].copy_from_slice(&counter.to_be_bytes());
+    header[9..13].copy_from_slice(&(length as u32).to_be_bytes());
+    header
+}
+
+/// Encrypts everything written to it into a framed stream on `inner`.
+///
+/// Call `finish` once all data has been written; a stream that is dropped
+/// without it has no final chunk and is rejected by `DecryptReader`.
+pub struct EncryptWriter<'a, W: Write> {
+    inner: W,
+    lattice: &'a Lattice,
+    seed: u64,
+    mac: Mac,
+    version: u8,
+    nonce: Nonce,
+    authenticated_header: Vec<u8>,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    counter: u64,
+}
+
+impl<'a, W: Write> EncryptWriter<'a, W> {
+    /// Create a writer with the default chunk size and write the stream header
+    pub fn new(inner: W, lattice: &'a Lattice, seed: u64, mac_key: &[u8], nonces: &mut NonceGenerator) -> Result<Self> {
+        Self::with_chunk_size(inner, lattice, seed, mac_key, DEFAULT_CHUNK_SIZE, nonces)
+    }
+
+    /// Create a writer with a custom chunk size and write the stream header,
+    /// with the stream nonce drawn from `nonces`
+    pub fn with_chunk_size(mut inner: W, lattice: &'a Lattice, seed: u64, mac_key: &[u8], chunk_size: usize, nonces: &mut NonceGenerator) -> Result<Self> {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(Error::Format(format!("Chunk size {} is out of range.", chunk_size)));
+        }
+
+        // Lattices on older schedules are described by the matching older version
+        let schedule = lattice.key_schedule();
+        let version = match (schedule.binding, schedule.sequence) {
+            (Binding::ConstantTime, SequenceAlgorithm::WideCatMap) => STREAM_VERSION,
+            (Binding::Legacy, SequenceAlgorithm::WideCatMap) => WIDE_CAT_MAP_STREAM_VERSION,
+            (Binding::Legacy, SequenceAlgorithm::CatMap) => CAT_MAP_STREAM_VERSION,
+            (Binding::Legacy, SequenceAlgorithm::Trigonometric) => TRIGONOMETRIC_STREAM_VERSION,
+            _ => return Err(Error::Format(format!("Streams cannot use the {:?} key schedule.", schedule))),
+        };
+
+        let nonce = if version == STREAM_VERSION { lattice.draw_nonce(seed, nonces)? } else { nonces.next_nonce()? };
+        let header = StreamHeader { version, chunk_size, nonce: nonce.as_bytes().to_vec() };
+        inner.write_all(&header.to_bytes())?;
+
+        Ok(Self {
+            inner,
+            lattice,
+            seed,
+            mac: chunk_mac(mac_key),
+            version,
+            nonce,
+            authenticated_header: header.authenticated_bytes(),
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size),
+            counter: 0,
+        })
+    }
+
+    /// Encrypt and write one frame from the buffered plaintext
+    fn write_chunk(&mut self, flags: u8) -> io::Result<()> {
+        let header = frame_header(flags, self.counter, self.buffer.len());
+        let ciphertext = self.lattice.encrypt_under(&self.buffer, chunk_sbox_seed(self.version, self.seed, self.counter), chunk_seed(self.seed, self.counter), &self.nonce);
+        let tag = self.mac.compute_parts(&[&self.authenticated_header, &header, &ciphertext]);
+
+        self.inner.write_all(&header)?;
+        self.inner.write_all(&ciphertext)?;
+        self.inner.write_all(&tag)?;
+
+        self.buffer.clear();
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| Error::Format("Chunk counter overflowed.".to_string()))?;
+        Ok(())
+    }
+
+    /// Write the final chunk and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.write_chunk(FINAL_CHUNK)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<'_, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() == self.chunk_size {
+            self.write_chunk(0)?;
+        }
+        let take = data.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&data[..take]);
+        Ok(take)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A parsed stream header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamHeader {
+    pub version: u8,
+    pub chunk_size: usize,
+    pub nonce: Vec<u8>,
+}
+
+impl StreamHeader {
+    /// Read and validate a stream header from `inner`
+    pub fn read<R: Read>(inner: &mut R) -> Result<Self> {
+        let mut header = [0u8; HEADER_LENGTH];
+        inner.read_exact(&mut header)?;
+
+        if &header[0..4] != STREAM_MAGIC {
+            return Err(Error::Format("Missing stream magic bytes.".to_string()));
+        }
+        let chunk_size = u32::from_be_bytes(header[5..9].try_into().unwrap()) as usize;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(Error::Format(format!("Chunk size {} is out of range.", chunk_size)));
+        }
+        let nonce = match header[4] {
+            STREAM_VERSION | CONSTANT_TIME_STREAM_VERSION | WIDE_CAT_MAP_STREAM_VERSION | CAT_MAP_STREAM_VERSION | TRIGONOMETRIC_STREAM_VERSION => {
+                let mut nonce = vec![0u8; NONCE_LENGTH];
+                inner.read_exact(&mut nonce)?;
+                nonce
+            }
+            LEGACY_STREAM_VERSION => Vec::new(),
+            version => return Err(Error::Format(format!("Unsupported stream version {}.", version))),
+        };
+
+        Ok(Self {
+            version: header[4],
+            chunk_size,
+            nonce,
+        })
+    }
+
+    /// Encode the header as it starts the stream
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LENGTH + self.nonce.len());
+        header.extend_from_slice(STREAM_MAGIC);
+        header.push(self.version);
+        header.extend_from_slice(&(self.chunk_size as u32).to_be_bytes());
+        header.extend_from_slice(&self.nonce);
+        header
+    }
+
+    /// The part of the header every chunk tag covers: all of it from
+    /// version 6 on, only the nonce before
+    fn authenticated_bytes(&self) -> Vec<u8> {
+        if self.version >= STREAM_VERSION {
+            self.to_bytes()
+        } else {
+            self.nonce.clone()
+        }
+    }
+
+    /// Key schedule the lattice must be built with
+    pub fn key_schedule(&self) -> KeySchedule {
+        match self.version {
+            STREAM_VERSION | CONSTANT_TIME_STREAM_VERSION => KeySchedule::LATEST,
+            WIDE_CAT_MAP_STREAM_VERSION => KeySchedule::legacy(SequenceAlgorithm::WideCatMap),
+            CAT_MAP_STREAM_VERSION => KeySchedule::legacy(SequenceAlgorithm::CatMap),
+            _ => KeySchedule::legacy(SequenceAlgorithm::Trigonometric),
+        }
+    }
+}
+
+/// Decrypts and authenticates a framed stream read from `inner`.
+///
+/// Reading fails with `ErrorKind::InvalidData` if a tag does not verify,
+/// chunks are out of order, or the stream ends before its final chunk.
+pub struct DecryptReader<'a, R: Read> {
+    inner: R,
+    lattice: &'a Lattice,
+    seed: u64,
+    mac: Mac,
+    version: u8,
+    nonce: Vec<u8>,
+    authenticated_header: Vec<u8>,
+    chunk_size: usize,
+    plaintext: Vec<u8>,
+    position: usize,
+    counter: u64,
+    finished: bool,
+}
+
+impl<'a, R: Read> DecryptReader<'a, R> {
+    /// Create a reader and validate the stream header
+    pub fn new(mut inner: R, lattice: &'a Lattice, seed: u64, mac_key: &[u8]) -> Result<Self> {
+        let header = StreamHeader::read(&mut inner)?;
+        Self::with_header(inner, header, lattice, seed, mac_key)
+    }
+
+    /// Create a reader for the frames following an already read header.
+    ///
+    /// The lattice must use the key schedule the header names.
+    pub fn with_header(inner: R, header: StreamHeader, lattice: &'a Lattice, seed: u64, mac_key: &[u8]) -> Result<Self> {
+        if header.key_schedule() != lattice.key_schedule() {
+            return Err(Error::Format(format!(
+                "Stream version {} needs the {:?} key schedule, but the lattice uses {:?}.",
+                header.version,
+                header.key_schedule(),
+                lattice.key_schedule()
+            )));
+        }
+        let authenticated_header = header.authenticated_bytes();
+        let StreamHeader { version, chunk_size, nonce } = header;
+
+        Ok(Self {
+            inner,
+            lattice,
+            seed,
+            mac: chunk_mac(mac_key),
+            version,
+            nonce,
+            authenticated_header,
+            chunk_size,
+            plaintext: Vec::new(),
+            position: 0,
+            counter: 0,
+            finished: false,
+        })
+    }
+
+    /// Fill `buf` from the stream, reporting a truncated stream as a format error
+    fn read_frame_part(&mut self, buf: &mut [u8]) -> Result<()> {
+        self.inner.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::Format("Stream ended before the final chunk.".to_string()),
+            _ => Error::Io(e),
+        })
+    }
+
+    /// Read, verify and decrypt the next frame into the plaintext buffer
+    fn read_chunk(&mut self) -> Result<()> {
+        let mut header = [0u8; FRAME_HEADER_LENGTH];
+        self.read_frame_part(&mut header)?;
+
+        let flags = header[0];
+        let counter = u64::from_be_bytes(header[1..9].try_into().unwrap());
+        let length = u32::from_be_bytes(header[9..13].try_into().unwrap()) as usize;
+
+        if flags & !FINAL_CHUNK != 0 {
+            return Err(Error::Format(format!("Unknown chunk flags {:#04x}.", flags)));
+        }
+        if counter != self.counter {
+            return Err(Error::Format(format!("Expected chunk {}, found chunk {}.", self.counter, counter)));
+        }
+        if length > self.chunk_size || (flags & FINAL_CHUNK == 0 && length != self.chunk_size) {
+            return Err(Error::Length {
+                expected: self.chunk_size,
+                found: length,
+            });
+        }
+
+        let mut ciphertext = vec![0u8; length];
+        let mut tag = [0u8; TAG_LENGTH];
+        self.read_frame_part(&mut ciphertext)?;
+        self.read_frame_part(&mut tag)?;
+
+        self.mac.verify_parts(&[&self.authenticated_header, &header, &ciphertext], &tag)?;
+
+        let seed = chunk_seed(self.seed, counter);
+        self.plaintext = if self.nonce.is_empty() {
+            self.lattice.decrypt(&ciphertext, seed)
+        } else {
+            self.lattice.decrypt_under(&ciphertext, chunk_sbox_seed(self.version, self.seed, counter), seed, &self.nonce)
+        };
+        self.position = 0;
+        self.counter += 1;
+
+        if flags & FINAL_CHUNK != 0 {
+            // Nothing may follow the final chunk
+            let mut trailing = [0u8; 1];
+            if self.inner.read(&mut trailing)? != 0 {
+                return Err(Error::Format("Data found after the final chunk.".to_string()));
+            }
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.plaintext.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.read_chunk()?;
+        }
+        let available = &self.plaintext[self.position..];
+        let take = available.len().min(buf.len());
+        buf[..take].copy_from_slice(&available[..take]);
+        self.position += take;
+        Ok(take)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyper_prime::small_prime_sieve;
+
+    fn test_lattice() -> Lattice {
+        Lattice::from_chaos_key_with_schedule(KeySchedule::LATEST, 7, b"hmac key", 4, 1, 32, &small_prime_sieve(1_000), 10).unwrap()
+    }
+
+    const V5_STREAM: &str = "4d434c5305000000081111111111111111111111111111111100000000000000000000000008f1219ee69e2bdef127955f1489c470bfd786192b13b0e8a0529628f4c80197d2db49620c9939e77100000000000000000100000008f7ca6a500772727254de59a82872d304f2d0b2cc47e6599e87401f4313c9fc59ad3027b9f53f3ae30100000000000000020000000385bf81b23b2d0cbd69c2cc70e7132d87928f5eddf8823fb3f8fd040e9af61777240c97";
+    const V6_STREAM: &str = "4d434c5306000000089a3744504560639ec670b7a17d492b2700000000000000000000000008989859d159145d89527567a2c56ea1bdbb1266d9d1a22acd9f091fae9897faeb9b341be18ae49c8700000000000000000100000008c6a4eca4749ed15ad6d22fde31bd32695e21a69dbe689ef0e3e218cd18bd53f9564578d7e7468c30010000000000000002000000039c039883d4e21add9214ccd9262683cedc463850286e7d6940019c4b9b26736476ebe0";
+
+    fn encrypt(lattice: &Lattice, data: &[u8]) -> Vec<u8> {
+        let mut writer = EncryptWriter::with_chunk_size(Vec::new(), lattice, 7, b"mac key", 100, &mut NonceGenerator::random()).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_stream_roundtrip() {
+        let lattice = test_lattice();
+        for len in [0, 1, 99, 100, 101, 1000] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 31 % 256) as u8).collect();
+            let stream = encrypt(&lattice, &data);
+
+            let mut reader = DecryptReader::new(&stream[..], &lattice, 7, b"mac key").unwrap();
+            let mut decrypted = Vec::new();
+            reader.read_to_end(&mut decrypted).unwrap();
+            assert_eq!(decrypted, data);
+        }
+
+        // Every stream gets its own nonce
+        assert_ne!(encrypt(&lattice, &[0u8; 64]), encrypt(&lattice, &[0u8; 64]));
+
+        // A lattice on the wrong key schedule is refused up front
+        let stream = encrypt(&lattice, b"data");
+        assert_eq!(StreamHeader::read(&mut &stream[..]).unwrap().key_schedule(), KeySchedule::LATEST);
+        let legacy = Lattice::from_chaos_key_with_schedule(KeySchedule::legacy(SequenceAlgorithm::WideCatMap), 7, b"hmac key", 4, 1, 32, &small_prime_sieve(1_000), 10).unwrap();
+        assert!(matches!(DecryptReader::new(&stream[..], &legacy, 7, b"mac key"), Err(Error::Format(_))));
+        let legacy_stream = encrypt(&legacy, b"data");
+        assert_eq!(legacy_stream[4], WIDE_CAT_MAP_STREAM_VERSION);
+        assert!(DecryptReader::new(&legacy_stream[..], &legacy, 7, b"mac key").is_ok());
+    }
+
+    #[test]
+    fn test_known_answer_streams() {
+        let lattice = test_lattice();
+        let decrypt = |stream: &[u8]| {
+            let mut decrypted = Vec::new();
+            DecryptReader::new(stream, &lattice, 7, b"mac key").unwrap().read_to_end(&mut decrypted).unwrap();
+            decrypted
+        };
+
+        // Version 5, written by an earlier release: one S-Box per chunk, only the nonce authenticated
+        let v5 = hex::decode(V5_STREAM).unwrap();
+        assert_eq!(StreamHeader::read(&mut &v5[..]).unwrap().key_schedule(), KeySchedule::LATEST);
+        assert_eq!(decrypt(&v5), b"known answer stream");
+
+        // Version 6: one checked S-Box per stream and the whole header authenticated
+        let mut writer = EncryptWriter::with_chunk_size(Vec::new(), &lattice, 7, b"mac key", 8, &mut NonceGenerator::deterministic(1)).unwrap();
+        writer.write_all(b"known answer stream").unwrap();
+        let v6 = writer.finish().unwrap();
+        assert_eq!(hex::encode(&v6), V6_STREAM);
+        assert_eq!(StreamHeader::read(&mut &v6[..]).unwrap().key_schedule(), KeySchedule::LATEST);
+        assert_eq!(decrypt(&v6), b"known answer stream");
+    }
+
+    #[test]
+    fn test_stream_rejects_tampering_and_truncation() {
+        let lattice = test_lattice();
+        let data = vec![0xabu8; 250];
+        let stream = encrypt(&lattice, &data);
+
+        let mut tampered = stream.clone();
+        tampered[HEADER_LENGTH + NONCE_LENGTH + FRAME_HEADER_LENGTH] ^= 1;
+        let mut reader = DecryptReader::new(&tampered[..], &lattice, 7, b"mac key").unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+        // The stream header is authenticated: a downgraded version fails the first tag
+        let mut downgraded = stream.clone();
+        downgraded[4] = CONSTANT_TIME_STREAM_VERSION;
+        let mut reader = DecryptReader::new(&downgraded[..], &lattice, 7, b"mac key").unwrap();
+        assert!(matches!(reader.read_chunk(), Err(Error::Authentication)));
+
+        // Drop the final chunk entirely
+        let truncated = &stream[..HEADER_LENGTH + NONCE_LENGTH + 2 * (FRAME_HEADER_LENGTH + 100 + TAG_LENGTH)];
+        let mut reader = DecryptReader::new(truncated, &lattice, 7, b"mac key").unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    }
+}